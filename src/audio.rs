@@ -2,34 +2,79 @@ use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use std::fs::File;
 use std::io::BufReader;
 
+/// Music volume while a menu is open over the game, relative to normal.
+const DUCKED_VOLUME: f32 = 0.3;
+
 pub struct Audio {
     _stream: OutputStream,
     handle: OutputStreamHandle,
     music_sink: Sink,
+    volume: f32,
+    ducked: bool,
+    muted: bool,
 }
 
 impl Audio {
     pub fn new() -> Self {
         let (_stream, handle) = OutputStream::try_default().unwrap();
         let music_sink = Sink::try_new(&handle).unwrap();
-        Self { _stream, handle, music_sink }
+        Self { _stream, handle, music_sink, volume: 1.0, ducked: false, muted: false }
+    }
+
+    /// Master volume from 0 to 1 for music and effects.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        self.apply_volume();
+    }
+
+    /// Lowers the music while the game is paused.
+    pub fn set_ducked(&mut self, ducked: bool) {
+        if self.ducked == ducked {
+            return;
+        }
+        self.ducked = ducked;
+        self.apply_volume();
+    }
+
+    /// Silences music and effects regardless of the volume setting.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply_volume();
+    }
+
+    fn apply_volume(&self) {
+        if self.muted {
+            self.music_sink.set_volume(0.0);
+            return;
+        }
+        let duck = if self.ducked { DUCKED_VOLUME } else { 1.0 };
+        self.music_sink.set_volume(self.volume * duck);
     }
 
     pub fn play_music(&mut self, file_path: &str) {
         self.music_sink.stop();
         self.music_sink = Sink::try_new(&self.handle).unwrap();
+        self.apply_volume();
 
-        let file = File::open(file_path).unwrap();
-        let source = Decoder::new(BufReader::new(file))
-            .unwrap()
-            .repeat_infinite();
-
-        self.music_sink.append(source);
+        if let Some(source) = decode(file_path) {
+            self.music_sink.append(source.repeat_infinite());
+        }
     }
 
     pub fn sfx(&self, file_path: &str) {
-        let file = File::open(file_path).unwrap();
-        let source = Decoder::new(BufReader::new(file)).unwrap();
-        self.handle.play_raw(source.convert_samples()).unwrap();
+        if self.muted {
+            return;
+        }
+        let Some(source) = decode(file_path) else {
+            return;
+        };
+        self.handle.play_raw(source.amplify(self.volume).convert_samples()).unwrap();
     }
 }
+
+/// Opens a sound file, reporting problems instead of failing, since level
+/// files and scripts can name any file.
+fn decode(file_path: &str) -> Option<Decoder<BufReader<File>>> {
+    let file = File::open(file_path).map_err(|err| eprintln!("Failed to open {}: {}", file_path, err)).ok()?;
+    Decoder::new(BufReader::new(file)).map_err(|err| eprintln!("Failed to decode {}: {}", file_path, err)).ok()
+}
@@ -0,0 +1,134 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::settings::config_path;
+
+pub const MANIFEST: &str = "levels/campaign.txt";
+
+pub struct CampaignLevel {
+    pub name: String,
+    pub file: String,
+}
+
+/// The levels played one after another, from a manifest of
+/// `Title = file` lines. Files are relative to the manifest.
+#[derive(Default)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+impl Campaign {
+    pub fn load(path: &str) -> io::Result<Campaign> {
+        let contents = fs::read_to_string(path)?;
+        let dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(Campaign::parse(path, &contents, &dir))
+    }
+
+    fn parse(name: &str, contents: &str, dir: &Path) -> Campaign {
+        let mut levels = Vec::new();
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((title, file)) if !file.trim().is_empty() => levels.push(CampaignLevel {
+                    name: title.trim().to_string(),
+                    file: dir.join(file.trim()).to_string_lossy().into_owned(),
+                }),
+                _ => eprintln!("{}: ignoring malformed line '{}'", name, line),
+            }
+        }
+        Campaign { levels }
+    }
+
+    /// Position of the level loaded from `file`, if it is part of the campaign.
+    pub fn index_of(&self, file: &str) -> Option<usize> {
+        self.levels.iter().position(|level| Path::new(&level.file) == Path::new(file))
+    }
+}
+
+/// How far the player has got through the campaign, kept between runs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Progress {
+    /// Number of levels that can be picked from level select; always at least one.
+    pub unlocked: usize,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress { unlocked: 1 }
+    }
+}
+
+impl Progress {
+    pub fn path() -> String {
+        config_path("progress.cfg")
+    }
+
+    pub fn load(path: &str) -> Progress {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Progress::default();
+        };
+        let unlocked = contents
+            .lines()
+            .find_map(|line| line.split_once('=').filter(|(key, _)| key.trim() == "unlocked"))
+            .and_then(|(_, value)| value.trim().parse().ok())
+            .unwrap_or(1);
+        Progress { unlocked: unlocked.max(1) }
+    }
+
+    pub fn save(&self, path: &str) {
+        if let Some(dir) = Path::new(path).parent()
+            && let Err(err) = fs::create_dir_all(dir)
+        {
+            eprintln!("Failed to create {}: {}", dir.display(), err);
+        }
+        if let Err(err) = fs::write(path, format!("unlocked = {}\n", self.unlocked)) {
+            eprintln!("Failed to save progress to {}: {}", path, err);
+        }
+    }
+
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        index < self.unlocked
+    }
+
+    /// Opens up the level after `completed`. Returns true if it was new.
+    pub fn complete(&mut self, completed: usize) -> bool {
+        if completed + 1 < self.unlocked {
+            return false;
+        }
+        self.unlocked = completed + 2;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::{load_level, validate_level};
+
+    #[test]
+    fn every_campaign_level_is_valid() {
+        let campaign = Campaign::load(MANIFEST).unwrap();
+        assert!(campaign.levels.len() >= 3);
+        for level in &campaign.levels {
+            let loaded = load_level(&level.file).unwrap();
+            assert_eq!(validate_level(&loaded), Vec::<String>::new(), "{}", level.file);
+        }
+        assert_eq!(campaign.index_of("levels/level2.txt"), Some(1));
+    }
+
+    #[test]
+    fn completing_a_level_unlocks_the_next_once() {
+        let mut progress = Progress::default();
+        assert!(progress.is_unlocked(0));
+        assert!(!progress.is_unlocked(1));
+
+        assert!(progress.complete(0));
+        assert!(progress.is_unlocked(1));
+        assert!(!progress.complete(0));
+        assert_eq!(progress.unlocked, 2);
+    }
+}
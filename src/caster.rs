@@ -1,6 +1,7 @@
 use raylib::color::Color;
+use raylib::math::Vector2;
 
-use crate::{framebuffer::Framebuffer, maze::Maze, player::Player};
+use crate::{framebuffer::Framebuffer, maze::{is_walkable, Maze}, player::Player};
 
 pub struct Intersect {
     pub distance: f32,
@@ -9,41 +10,69 @@ pub struct Intersect {
 }
 
 pub fn cast_ray(framebuffer: &mut Framebuffer, maze: &Maze, player: &Player, a: f32, block_size: usize, draw_line: bool) -> Intersect {
+    let intersect = cast_ray_from(maze, player.pos, a, block_size);
+
+    if draw_line {
+        framebuffer.set_current_color(Color::WHITESMOKE);
+        let mut d = 0.0;
+        while d < intersect.distance {
+            let x = player.pos.x + d * a.cos();
+            let y = player.pos.y + d * a.sin();
+            framebuffer.set_pixel(x as u32, y as u32);
+            d += 1.0;
+        }
+    }
+
+    intersect
+}
+
+/// Marches a ray from any point in the maze until it hits a wall. Leaving
+/// the maze counts as hitting a wall.
+pub fn cast_ray_from(maze: &Maze, origin: Vector2, a: f32, block_size: usize) -> Intersect {
     let mut d = 0.0;
-    framebuffer.set_current_color(Color::WHITESMOKE);
 
     loop {
         let cos = d * a.cos();
         let sin = d * a.sin();
-        let x = (player.pos.x + cos) as usize;
-        let y = (player.pos.y + sin) as usize;
+        let fx = origin.x + cos;
+        let fy = origin.y + sin;
+        if fx < 0.0 || fy < 0.0 {
+          return Intersect { distance: d, impact: '+', tx: 0 };
+        }
+        let x = fx as usize;
+        let y = fy as usize;
 
         let i = x / block_size;
         let j = y / block_size;
 
-        if maze[j][i] != ' ' {
+        let Some(&cell) = maze.get(j).and_then(|row| row.get(i)) else {
+          return Intersect { distance: d, impact: '+', tx: 0 };
+        };
+
+        if !is_walkable(cell) {
           let hitx = x - i*block_size;
           let hity = y - j*block_size;
           let mut maxhit = hity;
 
           if 1 < hitx && hitx < block_size - 1 {
             maxhit = hitx
-          } 
+          }
 
           let tx = (maxhit * 128) / block_size;
 
           return Intersect{
             distance: d,
-            impact: maze[j][i],
+            impact: cell,
             tx
           };
         }
 
-        if draw_line {
-          framebuffer.set_pixel(x as u32, y as u32);
-        }
-
         d += 1.0;
     }
 }
 
+/// Whether `to` can be seen from `from` without a wall in between.
+pub fn has_line_of_sight(maze: &Maze, from: Vector2, to: Vector2, block_size: usize) -> bool {
+    let a = (to.y - from.y).atan2(to.x - from.x);
+    cast_ray_from(maze, from, a, block_size).distance >= from.distance_to(to)
+}
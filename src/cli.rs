@@ -0,0 +1,175 @@
+use crate::level::LevelSource;
+
+pub const USAGE: &str = concat!(
+    "Usage: ",
+    env!("CARGO_BIN_NAME"),
+    " [OPTIONS]
+
+Options:
+  --level FILE           Play the level in FILE instead of the campaign
+  --seed N               Play a randomly generated maze built from seed N
+  --maze-size COLSxROWS  Rooms in a generated maze (default: 8x6)
+  --size WxH             Window size in pixels, overriding the settings
+  --block-size N         Size of a maze cell in world units (default: 100)
+  --fullscreen           Start in fullscreen
+  --render-scale F       Fraction of columns the raycaster draws, 0 to 1
+  --mute                 Disable music and sound effects
+  --headless OUT.png     Render the first frame to OUT.png and exit; with
+                         --replay, simulate the replay and render its last frame
+  --record FILE          Save the input of each run to FILE as a replay
+  --replay FILE          Play back a replay recorded with --record
+  --validate             Check the level, or every campaign level, and exit
+  --help                 Show this message
+"
+);
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    /// Level file from `--level`; without it and `--seed` the campaign is played.
+    pub level: Option<String>,
+    pub seed: Option<u64>,
+    pub maze_size: (usize, usize),
+    pub size: Option<(i32, i32)>,
+    pub block_size: usize,
+    pub fullscreen: bool,
+    pub render_scale: Option<f32>,
+    pub mute: bool,
+    pub headless: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub validate: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            level: None,
+            seed: None,
+            maze_size: (8, 6),
+            size: None,
+            block_size: 100,
+            fullscreen: false,
+            render_scale: None,
+            mute: false,
+            headless: None,
+            record: None,
+            replay: None,
+            validate: false,
+            help: false,
+        }
+    }
+}
+
+fn parse_pair<T: std::str::FromStr>(text: &str) -> Option<(T, T)> {
+    let (a, b) = text.split_once('x')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+/// Parses the arguments after the program name. Errors are meant to be
+/// printed above the usage text.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        let invalid = |name: &str, value: &str| format!("invalid value '{}' for {}", value, name);
+
+        match arg.as_str() {
+            "--level" => options.level = Some(value("--level")?),
+            "--seed" => {
+                let seed = value("--seed")?;
+                options.seed = Some(seed.parse().map_err(|_| invalid("--seed", &seed))?);
+            }
+            "--maze-size" => {
+                let size = value("--maze-size")?;
+                options.maze_size = parse_pair(&size)
+                    .filter(|&(cols, rows)| cols >= 2 && rows >= 2)
+                    .ok_or_else(|| invalid("--maze-size", &size))?;
+            }
+            "--size" => {
+                let size = value("--size")?;
+                let size = parse_pair(&size).filter(|&(w, h)| w > 0 && h > 0).ok_or_else(|| invalid("--size", &size))?;
+                options.size = Some(size);
+            }
+            "--block-size" => {
+                let block_size = value("--block-size")?;
+                options.block_size = block_size
+                    .parse()
+                    .ok()
+                    .filter(|&size| size >= 10)
+                    .ok_or_else(|| invalid("--block-size", &block_size))?;
+            }
+            "--render-scale" => {
+                let scale = value("--render-scale")?;
+                let parsed = scale.parse().ok().filter(|scale| *scale > 0.0 && *scale <= 1.0);
+                options.render_scale = Some(parsed.ok_or_else(|| invalid("--render-scale", &scale))?);
+            }
+            "--headless" => options.headless = Some(value("--headless")?),
+            "--record" => options.record = Some(value("--record")?),
+            "--replay" => options.replay = Some(value("--replay")?),
+            "--fullscreen" => options.fullscreen = true,
+            "--mute" => options.mute = true,
+            "--validate" => options.validate = true,
+            "--help" | "-h" => options.help = true,
+            other => return Err(format!("unknown option '{}'", other)),
+        }
+    }
+
+    if options.level.is_some() && options.seed.is_some() {
+        return Err("--level and --seed can't be used together".to_string());
+    }
+    if options.replay.is_some() && (options.level.is_some() || options.seed.is_some() || options.record.is_some()) {
+        return Err("--replay brings its own level and can't be combined with --level, --seed or --record".to_string());
+    }
+
+    Ok(options)
+}
+
+impl Options {
+    /// The level asked for on the command line, if any.
+    pub fn level_source(&self) -> Option<LevelSource> {
+        match (self.seed, &self.level) {
+            (Some(seed), _) => Some(LevelSource::Generated { cols: self.maze_size.0, rows: self.maze_size.1, seed }),
+            (None, Some(level)) => Some(LevelSource::File(level.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_means_defaults() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn values_are_parsed() {
+        let options = parse(&["--seed", "7", "--maze-size", "10x4", "--size", "800x600", "--render-scale", "0.5", "--mute", "--headless", "out.png"]).unwrap();
+
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.maze_size, (10, 4));
+        assert_eq!(options.size, Some((800, 600)));
+        assert_eq!(options.render_scale, Some(0.5));
+        assert!(options.mute);
+        assert_eq!(options.headless.as_deref(), Some("out.png"));
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(parse(&["--size", "800"]).is_err());
+        assert!(parse(&["--render-scale", "2"]).is_err());
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--level", "a.txt", "--seed", "1"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+        assert!(parse(&["--replay", "run.txt", "--seed", "1"]).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use raylib::prelude::*;
+use std::f32::consts::PI;
+
+use crate::caster::has_line_of_sight;
+use crate::maze::Maze;
+use crate::player::Player;
+use crate::timestep::{per_tick, ticks};
+
+pub const SWING_TICKS: u32 = ticks(0.3);
+/// Tick of the swing at which the blade connects.
+const STRIKE_TICK: u32 = ticks(2.0 / 15.0);
+/// Ticks after a swing starts before the next one can.
+const ATTACK_COOLDOWN: u32 = ticks(0.5);
+pub const HIT_RANGE: f32 = 110.0;
+pub const HIT_ARC: f32 = PI / 2.0;
+pub const DAMAGE: u32 = 1;
+pub const KNOCKBACK: f32 = per_tick(720.0);
+
+#[derive(Default)]
+pub struct Sword {
+    /// Ticks elapsed in the current swing, `None` while idle.
+    swing: Option<u32>,
+    cooldown: u32,
+}
+
+impl Sword {
+    /// Starts a swing unless the sword is still recovering from the last one.
+    pub fn try_swing(&mut self) -> bool {
+        if self.cooldown > 0 {
+            return false;
+        }
+        self.swing = Some(0);
+        self.cooldown = ATTACK_COOLDOWN;
+        true
+    }
+
+    /// Advances the swing by a tick. Returns true on the tick it strikes.
+    pub fn update(&mut self) -> bool {
+        self.cooldown = self.cooldown.saturating_sub(1);
+
+        let Some(tick) = self.swing else {
+            return false;
+        };
+        self.swing = (tick + 1 < SWING_TICKS).then_some(tick + 1);
+        tick == STRIKE_TICK
+    }
+
+    /// How far through the swing animation the sword is, from 0 to 1.
+    pub fn progress(&self) -> Option<f32> {
+        self.swing.map(|tick| tick as f32 / SWING_TICKS as f32)
+    }
+}
+
+/// Whether `target` is close enough and inside the arc in front of the
+/// player, with no wall in the way.
+pub fn in_hit_arc(maze: &Maze, player: &Player, target: Vector2, block_size: usize) -> bool {
+    if player.pos.distance_to(target) > HIT_RANGE {
+        return false;
+    }
+
+    let to_target = (target.y - player.pos.y).atan2(target.x - player.pos.x);
+    let difference = (to_target - player.a + PI).rem_euclid(2.0 * PI) - PI;
+    difference.abs() <= HIT_ARC / 2.0 && has_line_of_sight(maze, player.pos, target, block_size)
+}
@@ -0,0 +1,367 @@
+use raylib::prelude::*;
+use std::f32::consts::PI;
+
+use crate::caster::has_line_of_sight;
+use crate::maze::{cell_at, cell_center, slide, Maze};
+use crate::pathfinding::{find_path, heuristic, Cell};
+use crate::patrol::PatrolRoute;
+use crate::player::Player;
+use crate::sprites::Sprite;
+use crate::timestep::{per_tick, ticks};
+
+const PATROL_SPEED: f32 = per_tick(60.0);
+const CHASE_SPEED: f32 = per_tick(120.0);
+const SEARCH_SPEED: f32 = per_tick(90.0);
+const RETURN_SPEED: f32 = per_tick(60.0);
+const RADIUS: f32 = 15.0;
+/// Ticks an enemy waits between full A* searches. In between, paths are
+/// only patched when the goal moves to a neighbouring cell.
+const REPATH_INTERVAL: u32 = ticks(0.25);
+
+const SIGHT_RANGE: f32 = 450.0;
+const VIEW_CONE: f32 = PI * 2.0 / 3.0;
+/// Anything this close is noticed regardless of where the enemy faces.
+const TOUCH_RANGE: f32 = 60.0;
+const ALERT_TICKS: u32 = ticks(1.0 / 3.0);
+/// How long an enemy keeps hunting the last-known position after losing
+/// sight of the player.
+const MEMORY_TICKS: u32 = ticks(4.0);
+const LOOK_AROUND_SPEED: f32 = per_tick(PI * 2.0 / 3.0);
+
+pub const HEALTH: u32 = 3;
+const HURT_TICKS: u32 = ticks(1.0 / 6.0);
+const DEATH_TICKS: u32 = ticks(2.0 / 3.0);
+/// Fraction of knockback speed kept from one tick to the next.
+const KNOCKBACK_DAMPING: f32 = 0.87;
+/// Knockback slower than this has worn off.
+const KNOCKBACK_REST: f32 = per_tick(30.0);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnemyState {
+    Patrol,
+    Alert { ticks: u32 },
+    Chase,
+    Search { ticks: u32 },
+    Return,
+    Dying { ticks: u32 },
+}
+
+pub struct Enemy {
+    /// Kept for the whole level, so scripts can tell goblins apart.
+    pub id: usize,
+    pub sprite: Sprite,
+    pub spawn: Vector2,
+    pub route: PatrolRoute,
+    pub state: EnemyState,
+    /// Direction the enemy is looking, used for its view cone.
+    pub heading: f32,
+    pub last_known: Option<Vector2>,
+    pub health: u32,
+    /// Position before the last tick, for drawing between ticks.
+    pub previous: Vector2,
+    /// Whether this enemy has ever caught sight of the player.
+    pub spotted: bool,
+    knockback: Vector2,
+    hurt: u32,
+    path: Vec<Cell>,
+    goal: Option<Cell>,
+    repath_cooldown: u32,
+}
+
+impl EnemyState {
+    /// `patrol`, `alert:TICKS` and so on, for save files.
+    pub fn to_word(self) -> String {
+        match self {
+            EnemyState::Patrol => "patrol".to_string(),
+            EnemyState::Alert { ticks } => format!("alert:{}", ticks),
+            EnemyState::Chase => "chase".to_string(),
+            EnemyState::Search { ticks } => format!("search:{}", ticks),
+            EnemyState::Return => "return".to_string(),
+            EnemyState::Dying { ticks } => format!("dying:{}", ticks),
+        }
+    }
+
+    pub fn parse_word(word: &str) -> Option<EnemyState> {
+        let (name, ticks) = match word.split_once(':') {
+            Some((name, ticks)) => (name, Some(ticks.parse().ok()?)),
+            None => (word, None),
+        };
+        match (name, ticks) {
+            ("patrol", None) => Some(EnemyState::Patrol),
+            ("alert", Some(ticks)) => Some(EnemyState::Alert { ticks }),
+            ("chase", None) => Some(EnemyState::Chase),
+            ("search", Some(ticks)) => Some(EnemyState::Search { ticks }),
+            ("return", None) => Some(EnemyState::Return),
+            ("dying", Some(ticks)) => Some(EnemyState::Dying { ticks }),
+            _ => None,
+        }
+    }
+}
+
+impl Enemy {
+    pub fn new(id: usize, x: f32, y: f32, route: PatrolRoute) -> Self {
+        Enemy {
+            id,
+            sprite: Sprite::new(x, y, 0, 0, 'g', 64, 64),
+            spawn: Vector2::new(x, y),
+            route,
+            state: EnemyState::Patrol,
+            heading: 0.0,
+            last_known: None,
+            health: HEALTH,
+            previous: Vector2::new(x, y),
+            spotted: false,
+            knockback: Vector2::zero(),
+            hurt: 0,
+            path: Vec::new(),
+            goal: None,
+            repath_cooldown: 0,
+        }
+    }
+
+    pub fn pos(&self) -> Vector2 {
+        self.sprite.pos
+    }
+
+    /// Sends the enemy back to where it started, forgetting the player.
+    /// Damage it has taken is kept.
+    pub fn reset_to_spawn(&mut self) {
+        self.sprite.pos = self.spawn;
+        self.previous = self.spawn;
+        self.state = EnemyState::Patrol;
+        self.last_known = None;
+        self.knockback = Vector2::zero();
+        self.stop();
+    }
+
+    pub fn is_dying(&self) -> bool {
+        matches!(self.state, EnemyState::Dying { .. })
+    }
+
+    /// Whether the death animation has finished and the enemy can be removed.
+    pub fn is_dead(&self) -> bool {
+        self.state == EnemyState::Dying { ticks: 0 }
+    }
+
+    /// Applies a sword hit coming from `attacker`, pushing the enemy away.
+    pub fn take_hit(&mut self, damage: u32, attacker: Vector2, knockback: f32) {
+        if self.is_dying() {
+            return;
+        }
+
+        self.health = self.health.saturating_sub(damage);
+        self.hurt = HURT_TICKS;
+        let away = self.sprite.pos - attacker;
+        if away.length() > 0.0 {
+            self.knockback = away / away.length() * knockback;
+        }
+        self.stop();
+
+        if self.health == 0 {
+            self.state = EnemyState::Dying { ticks: DEATH_TICKS };
+            return;
+        }
+
+        self.last_known = Some(attacker);
+        self.state = EnemyState::Search { ticks: MEMORY_TICKS };
+    }
+
+    pub fn can_see(&self, maze: &Maze, target: Vector2, block_size: usize) -> bool {
+        let distance = self.sprite.pos.distance_to(target);
+        if distance > SIGHT_RANGE {
+            return false;
+        }
+
+        if distance > TOUCH_RANGE {
+            let to_target = (target.y - self.sprite.pos.y).atan2(target.x - self.sprite.pos.x);
+            let mut difference = to_target - self.heading;
+            while difference > PI {
+                difference -= 2.0 * PI;
+            }
+            while difference < -PI {
+                difference += 2.0 * PI;
+            }
+            if difference.abs() > VIEW_CONE / 2.0 {
+                return false;
+            }
+        }
+
+        has_line_of_sight(maze, self.sprite.pos, target, block_size)
+    }
+
+    pub fn can_hear(&self, player: &Player) -> bool {
+        player.noise > 0.0 && self.sprite.pos.distance_to(player.pos) <= player.noise
+    }
+
+    fn retarget(&mut self, maze: &Maze, goal: Cell, block_size: usize) {
+        if self.goal == Some(goal) {
+            return;
+        }
+
+        if let Some(old) = self.goal
+            && heuristic(old, goal) == 1
+        {
+            match self.path.iter().position(|&cell| cell == goal) {
+                Some(i) => self.path.truncate(i + 1),
+                None => self.path.push(goal),
+            }
+            self.goal = Some(goal);
+            return;
+        }
+
+        if self.repath_cooldown > 0 {
+            return;
+        }
+        self.repath_cooldown = REPATH_INTERVAL;
+
+        let start = cell_at(self.sprite.pos, block_size);
+        match find_path(maze, start, goal) {
+            Some(path) => {
+                self.path = path;
+                self.goal = Some(goal);
+            }
+            None => self.stop(),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.path.clear();
+        self.goal = None;
+    }
+
+    /// Walks along the current path and, once in the goal cell, straight at
+    /// `target`. Returns true when `target` has been reached.
+    fn follow_path(&mut self, maze: &Maze, target: Vector2, speed: f32, block_size: usize) -> bool {
+        if self.goal.is_none() {
+            return false;
+        }
+
+        while let Some(&next) = self.path.first() {
+            if self.sprite.pos.distance_to(cell_center(next, block_size)) > speed {
+                break;
+            }
+            self.path.remove(0);
+        }
+
+        let waypoint = match self.path.first() {
+            Some(&next) => cell_center(next, block_size),
+            None => target,
+        };
+
+        let to_waypoint = waypoint - self.sprite.pos;
+        let distance = to_waypoint.length();
+        if distance <= speed {
+            self.sprite.pos = slide(maze, self.sprite.pos, to_waypoint, RADIUS, block_size);
+            return self.path.is_empty();
+        }
+
+        self.heading = to_waypoint.y.atan2(to_waypoint.x);
+        let delta = to_waypoint / distance * speed;
+        self.sprite.pos = slide(maze, self.sprite.pos, delta, RADIUS, block_size);
+        false
+    }
+
+    fn move_to(&mut self, maze: &Maze, target: Vector2, speed: f32, block_size: usize) -> bool {
+        self.retarget(maze, cell_at(target, block_size), block_size);
+        self.follow_path(maze, target, speed, block_size)
+    }
+
+    /// Where the enemy goes back to after losing the player: its current
+    /// patrol waypoint, or its spawn when it has no route.
+    fn post(&self, block_size: usize) -> Vector2 {
+        match self.route.current() {
+            Some(waypoint) => cell_center(waypoint, block_size),
+            None => self.spawn,
+        }
+    }
+
+    fn face(&mut self, target: Vector2) {
+        self.heading = (target.y - self.sprite.pos.y).atan2(target.x - self.sprite.pos.x);
+    }
+
+    /// Runs one tick of the perception state machine. Returns true on the
+    /// tick the enemy first notices the player.
+    pub fn update(&mut self, maze: &Maze, block_size: usize, player: &Player) -> bool {
+        if let EnemyState::Dying { ticks } = self.state {
+            self.state = EnemyState::Dying { ticks: ticks.saturating_sub(1) };
+            self.sprite.scale = ticks as f32 / DEATH_TICKS as f32;
+            self.sprite.tint = Color::RED;
+            return false;
+        }
+
+        self.hurt = self.hurt.saturating_sub(1);
+        self.sprite.tint = if self.hurt > 0 { Color::RED } else { Color::WHITE };
+
+        // Staggered enemies slide back and don't act until the push fades.
+        if self.knockback.length() > KNOCKBACK_REST {
+            self.sprite.pos = slide(maze, self.sprite.pos, self.knockback, RADIUS, block_size);
+            self.knockback *= KNOCKBACK_DAMPING;
+            return false;
+        }
+
+        self.repath_cooldown = self.repath_cooldown.saturating_sub(1);
+
+        let sees = player.invisible == 0 && self.can_see(maze, player.pos, block_size);
+        let hears = self.can_hear(player);
+        if sees || hears {
+            self.last_known = Some(player.pos);
+        }
+
+        let previous = self.state;
+        self.state = match self.state {
+            EnemyState::Dying { ticks } => EnemyState::Dying { ticks },
+            EnemyState::Patrol | EnemyState::Return if sees => EnemyState::Alert { ticks: ALERT_TICKS },
+            EnemyState::Patrol | EnemyState::Return if hears => EnemyState::Search { ticks: MEMORY_TICKS },
+            EnemyState::Patrol => {
+                match self.route.current() {
+                    Some(waypoint) => {
+                        let target = cell_center(waypoint, block_size);
+                        if self.move_to(maze, target, PATROL_SPEED, block_size) {
+                            self.route.advance();
+                        }
+                    }
+                    None => self.heading = (self.heading + LOOK_AROUND_SPEED).rem_euclid(2.0 * PI),
+                }
+                EnemyState::Patrol
+            }
+            EnemyState::Alert { ticks } => {
+                self.face(player.pos);
+                match ticks {
+                    0 if sees => EnemyState::Chase,
+                    0 => EnemyState::Search { ticks: MEMORY_TICKS },
+                    _ => EnemyState::Alert { ticks: ticks - 1 },
+                }
+            }
+            EnemyState::Chase => {
+                if sees {
+                    self.move_to(maze, player.pos, CHASE_SPEED, block_size);
+                    EnemyState::Chase
+                } else {
+                    EnemyState::Search { ticks: MEMORY_TICKS }
+                }
+            }
+            EnemyState::Search { .. } if sees => EnemyState::Chase,
+            EnemyState::Search { ticks: 0 } => EnemyState::Return,
+            EnemyState::Search { ticks } => {
+                if let Some(last_known) = self.last_known
+                    && self.move_to(maze, last_known, SEARCH_SPEED, block_size)
+                {
+                    self.heading = (self.heading + LOOK_AROUND_SPEED * 2.0).rem_euclid(2.0 * PI);
+                }
+                EnemyState::Search { ticks: ticks - 1 }
+            }
+            EnemyState::Return => {
+                if self.move_to(maze, self.post(block_size), RETURN_SPEED, block_size) {
+                    self.stop();
+                    self.last_known = None;
+                    EnemyState::Patrol
+                } else {
+                    EnemyState::Return
+                }
+            }
+        };
+
+        matches!(self.state, EnemyState::Alert { .. } | EnemyState::Chase)
+            && !matches!(previous, EnemyState::Alert { .. } | EnemyState::Chase)
+    }
+}
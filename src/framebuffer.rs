@@ -38,15 +38,24 @@ impl Framebuffer {
         self.current_color = color;
     }
 
-    pub fn swap_buffers(
+    /// Presents the frame with an optional translucent color blended over it,
+    /// which the CPU-side image can't do by itself, then lets `draw_ui` draw
+    /// menus or text on top.
+    pub fn present(
         &self,
         window: &mut RaylibHandle,
         raylib_thread: &RaylibThread,
+        overlay: Option<Color>,
+        draw_ui: impl FnOnce(&mut RaylibDrawHandle),
     ) {
         if let Ok(texture) = window.load_texture_from_image(raylib_thread, &self.color_buffer) {
             let mut renderer = window.begin_drawing(raylib_thread);
             renderer.draw_texture(&texture, 0, 0, Color::WHITE);
+            if let Some(color) = overlay {
+                renderer.draw_rectangle(0, 0, self.width as i32, self.height as i32, color);
+            }
             renderer.draw_text(&format!("FPS: {}", renderer.get_fps()), 10, 10, 20, Color::WHITE);
+            draw_ui(&mut renderer);
         }
     }
 }
@@ -1,159 +1,635 @@
 use crate::audio::Audio;
+use crate::campaign::{Campaign, Progress};
 use crate::framebuffer::Framebuffer;
-use crate::maze::load_maze;
-use crate::player::{process_events, Player};
+use crate::hazard::Hazard;
+use crate::input::{Action, Input, InputState, Rebinder};
+use crate::inventory::{Item, KeyColor};
+use crate::cli::Options;
+use crate::level::{Level, LevelSource};
+use crate::minimap::MinimapConfig;
+use crate::pickup::PickupKind;
 use crate::renderer::*;
-use crate::sprites::Sprite;
+use crate::replay::{Playback, Replay};
+use crate::rng::seed_from_time;
+use crate::score::{format_time, type_name, HighScore, HighScores, Score};
+use crate::save::{describe_slot, load_world, save_world, slot_path, QUICK_SLOT, SLOTS};
+use crate::settings::{config_path, Settings, SettingsEvent, SettingsMenu};
+use crate::state::{Effect, GameState, StateMachine};
 use crate::textures::TextureManager;
-use raylib::math::Vector2;
+use crate::trigger::TriggerAction;
+use crate::timestep::{FixedTimestep, TICK};
+use crate::world::{World, WorldEvent};
 use raylib::prelude::*;
-use std::f32::consts::PI;
 
-pub enum GameState {
-    MainMenu,
-    Playing,
-    Win,
-    GameOver
+#[derive(Clone, Copy, PartialEq)]
+pub enum ViewMode {
+    FirstPerson,
+    Map
 }
 
+const PAUSE_OVERLAY: Color = Color::new(0, 0, 0, 170);
+/// Frames a notice like "Game saved" stays on screen.
+const NOTICE_FRAMES: u32 = 120;
+/// Level messages from triggers and scripts stay up longer, to be read.
+const MESSAGE_FRAMES: u32 = 300;
+/// Plays unless a trigger switched the music.
+const MUSIC: &str = "assets/music.wav";
+
 pub struct Game {
     window: RaylibHandle,
     raylib_thread: RaylibThread,
     framebuffer: Framebuffer,
-    player: Player,
     texture_manager: TextureManager,
-    block_size: usize,
-    game_state: GameState,
-    minimap_pos: Vector2,
-    enemies: Vec<Sprite>,
-    princess: Sprite,
-    audio: Audio
+    level: Level,
+    source: LevelSource,
+    /// Level from the command line or a replay, played by New game instead
+    /// of the first campaign level.
+    custom_level: Option<LevelSource>,
+    campaign: Campaign,
+    progress: Progress,
+    high_scores: HighScores,
+    /// Name typed for the last high score, offered again for the next one.
+    player_name: String,
+    /// Place of the finished run in the high-score table, once recorded.
+    new_rank: Option<usize>,
+    world: World,
+    states: StateMachine,
+    view_mode: ViewMode,
+    minimap: MinimapConfig,
+    audio: Audio,
+    input: Input,
+    rebinder: Rebinder,
+    settings: Settings,
+    settings_menu: SettingsMenu,
+    editing_controls: bool,
+    timestep: FixedTimestep,
+    /// Input from frames too short to run a tick, so their presses still land.
+    unticked: InputState,
+    /// Where to save the replay of each run, from `--record`.
+    record_path: Option<String>,
+    recording: Option<Replay>,
+    /// Recorded input that replaces the player's, from `--replay`.
+    playback: Option<Playback>,
+    /// Message shown over the game for a few frames, and frames left.
+    notice: Option<(String, u32)>,
+    /// Music file playing now.
+    music: String,
+    /// What each save slot holds, read when a slot menu opens.
+    slots: Vec<String>,
 }
 
 impl Game {
-    pub fn new(window_width: i32, window_height: i32, block_size: usize) -> Self {
-        let (mut window, raylib_thread) = raylib::init()
-            .size(window_width, window_height)
-            .title("Knight Maze")
-            .log_level(TraceLogLevel::LOG_WARNING)
-            .build();
-
-        window.set_target_fps(60);
-
-        let framebuffer = Framebuffer::new(window_width as u32, window_height as u32);
-
-        let minimap_pos = Vector2::new(1030.0, 10.0);
-
-        let player = Player {
-            pos: Vector2 {
-                x: 150.0,
-                y: 150.0,
-            },
-            a: PI / 3.0,
-            fov: PI / 3.0,
-            lives: 3
-        };
+    pub fn new(settings: Settings, level: Level, source: LevelSource, campaign: Campaign, options: &Options, replay: Option<Replay>) -> Self {
+        let mut builder = raylib::init();
+        builder.size(settings.width, settings.height).title("Knight Maze").log_level(TraceLogLevel::LOG_WARNING);
+        if options.fullscreen {
+            builder.fullscreen();
+        }
+        let (mut window, raylib_thread) = builder.build();
+
+        window.set_target_fps(settings.target_fps);
+        // Escape pauses instead of closing the window; quitting goes through the menus.
+        window.set_exit_key(None);
+
+        let framebuffer = Framebuffer::new(settings.width as u32, settings.height as u32);
+
+        let block_size = replay.as_ref().map_or(options.block_size, |replay| replay.block_size);
+        let mut world = World::new(&level, block_size, 0);
+        world.player.fov = settings.fov_radians();
+        let minimap = MinimapConfig::new(&settings);
 
         let texture_manager = TextureManager::new(&mut window, &raylib_thread);
 
-        let mut enemies = Vec::new();
-        enemies.push(Sprite::new(1090.0, 165.0, 0, 0, 'g', 64, 64));
-        enemies.push(Sprite::new(180.0, 690.0, 0, 0, 'g', 64, 64));
-        enemies.push(Sprite::new(1070.0, 590.0, 0, 0, 'g', 64, 64));
-        enemies.push(Sprite::new(500.0, 420.0, 0, 0, 'g', 64, 64));
+        let mut audio = Audio::new();
+        audio.set_volume(settings.volume);
+        audio.set_muted(options.mute);
 
-        let princess = Sprite::new(500.0, 500.0, 0, 0, 'p', 14, 35);
+        let custom_level = (replay.is_some() || options.level_source().is_some()).then(|| source.clone());
+        let mut states = StateMachine::default();
+        states.levels = campaign.levels.len();
 
-        Self {
+        let mut game = Self {
             window,
             raylib_thread,
             framebuffer,
-            player,
             texture_manager,
-            block_size,
-            game_state: GameState::MainMenu,
-            minimap_pos,
-            enemies,
-            princess,
-            audio: Audio::new()
+            level,
+            source,
+            custom_level,
+            campaign,
+            progress: Progress::load(&Progress::path()),
+            high_scores: HighScores::load(&HighScores::path()),
+            player_name: String::new(),
+            new_rank: None,
+            world,
+            states,
+            view_mode: ViewMode::FirstPerson,
+            minimap,
+            audio,
+            input: Input::new(&config_path("controls.cfg")),
+            rebinder: Rebinder::default(),
+            settings,
+            settings_menu: SettingsMenu::default(),
+            editing_controls: false,
+            timestep: FixedTimestep::default(),
+            unticked: InputState::default(),
+            record_path: options.record.clone(),
+            recording: None,
+            playback: replay.map(Playback::new),
+            notice: None,
+            music: String::new(),
+            slots: Vec::new(),
+        };
+
+        if game.playback.is_some() {
+            game.states.start();
+            game.reset_world();
+        }
+        game
+    }
+
+    /// Starts a new run. Replays reuse their recorded seed; otherwise every
+    /// run gets a fresh one and, with `--record`, a fresh recording.
+    fn reset_world(&mut self) {
+        let block_size = self.world.block_size;
+        let seed = match &mut self.playback {
+            Some(playback) => {
+                playback.restart();
+                playback.replay.seed
+            }
+            None => seed_from_time(),
+        };
+        if self.record_path.is_some() {
+            self.recording = Some(Replay::new(self.source.clone(), block_size, seed));
+        }
+
+        self.world = World::new(&self.level, block_size, seed);
+        self.world.player.fov = self.settings.fov_radians();
+        self.view_mode = ViewMode::FirstPerson;
+        self.timestep = FixedTimestep::default();
+        self.new_rank = None;
+    }
+
+    /// Switches to the music the world asks for: the usual track, or what a
+    /// trigger changed it to.
+    fn update_music(&mut self) {
+        let music = self.world.music.as_deref().unwrap_or(MUSIC);
+        if self.music != music {
+            self.music = music.to_string();
+            self.audio.play_music(music);
+        }
+    }
+
+    /// Position of the current level in the campaign, if it is part of it.
+    fn campaign_index(&self) -> Option<usize> {
+        match &self.source {
+            LevelSource::File(path) => self.campaign.index_of(path),
+            LevelSource::Generated { .. } => None,
+        }
+    }
+
+    /// Loads another level and starts playing it from the beginning.
+    fn play(&mut self, source: LevelSource) {
+        match source.load() {
+            Ok(level) => {
+                self.source = source;
+                self.level = level;
+                self.states.start();
+                self.reset_world();
+            }
+            Err(err) => {
+                eprintln!("Failed to load level ({}): {}", source.to_line(), err);
+                self.notice = Some(("Could not load that level".to_string(), NOTICE_FRAMES));
+            }
+        }
+    }
+
+    fn play_level(&mut self, index: usize) {
+        match self.campaign.levels.get(index) {
+            Some(level) if self.progress.is_unlocked(index) => self.play(LevelSource::File(level.file.clone())),
+            _ => self.notice = Some(("Finish the earlier levels first".to_string(), NOTICE_FRAMES)),
+        }
+    }
+
+    fn new_game(&mut self) {
+        match self.custom_level.clone() {
+            Some(source) => self.play(source),
+            None => self.play_level(0),
+        }
+    }
+
+    /// Records a finished campaign level and returns whether another one follows.
+    fn complete_level(&mut self) -> bool {
+        let Some(index) = self.campaign_index() else {
+            return false;
+        };
+        if self.progress.complete(index) {
+            self.progress.save(&Progress::path());
         }
+        index + 1 < self.campaign.levels.len()
+    }
+
+    fn score(&self) -> Score {
+        Score::new(&self.world, self.level.enemies.len())
+    }
+
+    fn record_score(&mut self) {
+        let name = self.player_name.trim();
+        let entry = HighScore {
+            name: if name.is_empty() { "Knight".to_string() } else { name.to_string() },
+            score: self.score().total(),
+            time: self.world.elapsed,
+        };
+        self.new_rank = self.high_scores.insert(&self.source, entry);
+        self.high_scores.save(&HighScores::path());
+    }
+
+    /// Title and stats for the level-complete screen.
+    fn completion_lines(&self) -> Vec<String> {
+        let title = match self.campaign_index() {
+            Some(index) if index + 1 == self.campaign.levels.len() => "Campaign complete!".to_string(),
+            Some(index) => format!("{} complete", self.campaign.levels[index].name),
+            None => "Level complete".to_string(),
+        };
+        let stats = &self.world.stats;
+        let enemies = self.level.enemies.len();
+        let score = self.score();
+        vec![
+            title,
+            format!("Time  {}", format_time(self.world.elapsed)),
+            format!("Deaths  {}", stats.deaths),
+            format!("Enemies avoided  {} / {}", enemies.saturating_sub(stats.spotted_by as usize), enemies),
+            format!("Score  {}", score.total()),
+            format!(
+                "time {} + lives {} + stealth {} + goblins {} + coins {}",
+                score.time, score.lives, score.stealth, score.kills, score.coins
+            ),
+        ]
+    }
+
+    /// The level's high-score table, with the run just recorded marked.
+    fn high_score_lines(&self) -> Vec<String> {
+        let table = self.high_scores.table(&self.source);
+        if table.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = vec!["High scores".to_string()];
+        for (i, entry) in table.iter().enumerate() {
+            let marker = if self.new_rank == Some(i) { "  <" } else { "" };
+            lines.push(format!("{}. {}  {}  {}{}", i + 1, entry.name, entry.score, format_time(entry.time), marker));
+        }
+        lines
     }
 
     pub fn run(&mut self) {
         self.framebuffer.set_background_color(Color::BLUE);
 
-        self.audio.play_music("assets/music.wav");
         while !self.window.window_should_close() {
-            self.framebuffer.clear();
+            let input = self.input.poll(&self.window);
+            self.update_music();
+
+            // Screens only react to input from the frame after they were entered.
+            let state = self.states.state;
+            match self.states.update(&input) {
+                Some(Effect::Quit) => break,
+                Some(Effect::NewGame) => self.new_game(),
+                Some(Effect::NextLevel) => self.play_level(self.campaign_index().map_or(0, |index| index + 1)),
+                Some(Effect::PlayLevel(index)) => self.play_level(index),
+                Some(Effect::RecordScore) => self.record_score(),
+                Some(Effect::ResetWorld) => self.reset_world(),
+                Some(Effect::Save(slot)) => self.save_game(slot),
+                Some(Effect::Load(slot)) => self.load_game(slot),
+                None => {}
+            }
+            if self.states.state != state && matches!(self.states.state, GameState::SaveGame | GameState::LoadGame) {
+                self.read_slots();
+            }
+            self.audio.set_ducked(self.states.in_game() && self.states.state != GameState::Playing);
 
-            match self.game_state {
+            let width = self.framebuffer.width;
+            let entries = self.states.menu();
+            let selected = self.states.menu_index;
+            let prompt = format!("{}: select", self.input.bindings.prompt(Action::Confirm, input.using_gamepad));
+
+            match self.states.state {
                 GameState::MainMenu => {
                     let mut d = self.window.begin_drawing(&self.raylib_thread);
-                    render_start(&mut d, self.framebuffer.width, &self.texture_manager);
-                    if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
-                        self.game_state = GameState::Playing;
+                    render_start(&mut d, width, &self.texture_manager, entries, selected, &prompt);
+                }
+                GameState::LevelSelect => {
+                    let mut names: Vec<&str> = self.campaign.levels.iter().map(|level| level.name.as_str()).collect();
+                    names.push("Back");
+                    let descriptions: Vec<String> = (0..self.campaign.levels.len())
+                        .map(|index| if self.progress.is_unlocked(index) { String::new() } else { "Locked".to_string() })
+                        .collect();
+                    let height = self.framebuffer.height;
+                    let mut d = self.window.begin_drawing(&self.raylib_thread);
+                    d.clear_background(Color::BLACK);
+                    render_slots(&mut d, width, "Select level", &names, &descriptions, selected, &prompt);
+                    if let Some((notice, _)) = &self.notice {
+                        render_notice(&mut d, width, height, notice);
+                    }
+                }
+                GameState::Settings => {
+                    if state == GameState::Settings {
+                        self.update_settings(&input);
+                    }
+
+                    let height = self.framebuffer.height;
+                    let width = self.framebuffer.width;
+                    let mut d = self.window.begin_drawing(&self.raylib_thread);
+                    if self.editing_controls {
+                        render_controls(&mut d, width, height, &self.input.bindings, &self.rebinder, input.using_gamepad);
+                    } else {
+                        let prompt = format!(
+                            "{}: change   {}: back",
+                            self.input.bindings.prompt(Action::Confirm, input.using_gamepad),
+                            self.input.bindings.prompt(Action::Back, input.using_gamepad)
+                        );
+                        render_settings(&mut d, width, &self.settings, &self.settings_menu, &prompt);
                     }
                 }
-                GameState::Win => {
+                GameState::Credits => {
+                    let prompt = format!("{}: back", self.input.bindings.prompt(Action::Back, input.using_gamepad));
+                    let mut d = self.window.begin_drawing(&self.raylib_thread);
+                    render_credits(&mut d, width, &prompt);
+                }
+                GameState::SaveGame => {
+                    let slots = &self.slots;
+                    self.framebuffer.present(&mut self.window, &self.raylib_thread, Some(PAUSE_OVERLAY), |d| {
+                        render_slots(d, width, "Save game", entries, slots, selected, &prompt);
+                    });
+                }
+                GameState::LoadGame => {
+                    let height = self.framebuffer.height;
                     let mut d = self.window.begin_drawing(&self.raylib_thread);
-                    render_victory(&mut d, self.framebuffer.width, &self.texture_manager);
-                    if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
-                        self.game_state = GameState::Playing;
+                    d.clear_background(Color::BLACK);
+                    render_slots(&mut d, width, "Load game", entries, &self.slots, selected, &prompt);
+                    if let Some((notice, _)) = &self.notice {
+                        render_notice(&mut d, width, height, notice);
                     }
                 }
+                GameState::Paused => {
+                    // The framebuffer still holds the last gameplay frame, so the
+                    // menu is drawn over a dimmed copy of it.
+                    self.framebuffer.present(&mut self.window, &self.raylib_thread, Some(PAUSE_OVERLAY), |d| {
+                        render_pause(d, width, entries, selected, &prompt);
+                    });
+                }
+                GameState::EnterName => {
+                    if state == GameState::EnterName {
+                        while let Some(typed) = self.window.get_char_pressed() {
+                            type_name(&mut self.player_name, typed);
+                        }
+                        if input.is_pressed(Action::Back) {
+                            self.player_name.pop();
+                        }
+                    }
+
+                    let prompt = format!("Type your name   {}: save", self.input.bindings.prompt(Action::Confirm, input.using_gamepad));
+                    let score = self.score().total();
+                    let mut d = self.window.begin_drawing(&self.raylib_thread);
+                    render_name_entry(&mut d, width, score, &self.player_name, &prompt);
+                }
+                GameState::LevelComplete => {
+                    let lines = self.completion_lines();
+                    let high_scores = self.high_score_lines();
+                    let mut d = self.window.begin_drawing(&self.raylib_thread);
+                    render_victory(&mut d, width, &self.texture_manager, &lines, &high_scores, entries, selected, &prompt);
+                }
                 GameState::GameOver => {
                     let mut d = self.window.begin_drawing(&self.raylib_thread);
-                    render_game_over(&mut d, self.framebuffer.width, &self.texture_manager);
+                    render_game_over(&mut d, width, &self.texture_manager, entries, selected, &prompt);
                 }
                 GameState::Playing => {
-                    let maze = load_maze("maze.txt");
+                    if state == GameState::Playing {
+                        self.update_playing(&input);
+                    }
+                    self.render_playing(&input);
+                }
+            }
 
-                    process_events(&self.window, &mut self.player, &maze, self.block_size);
+            if let Some((_, frames)) = &mut self.notice {
+                *frames = frames.saturating_sub(1);
+                if *frames == 0 {
+                    self.notice = None;
+                }
+            }
+        }
 
-                    for enemy in &mut self.enemies {
-                        let dist = self.player.pos.distance_to(enemy.pos);
-                        let dx = self.player.pos.x - enemy.pos.x;
-                        let dy = self.player.pos.y - enemy.pos.y;
+        // Runs that were quit halfway are worth keeping too.
+        self.save_recording();
+    }
 
-                        if dist < 200.0 {
-                            self.audio.sfx("assets/goblin.wav");
-                            enemy.pos.x += dx / dist * 2.0;
-                            enemy.pos.y += dy / dist * 2.0;
-                        }
+    fn update_settings(&mut self, input: &InputState) {
+        if self.editing_controls {
+            if self.rebinder.update(&mut self.window, &mut self.input, input) {
+                self.editing_controls = false;
+            }
+            return;
+        }
 
-                        if dist < 30.0 {
-                            self.player.lives -= 1;
-                            self.player.pos = Vector2::new(150.0, 150.0);
-                            if self.player.lives == 0 {
-                                self.game_state = GameState::GameOver;
-                            }
+        match self.settings_menu.update(input, &mut self.settings) {
+            Some(SettingsEvent::Changed) => {
+                self.apply_settings();
+                self.settings.save(&Settings::path());
+            }
+            Some(SettingsEvent::OpenControls) => self.editing_controls = true,
+            Some(SettingsEvent::Close) => self.states.close_settings(),
+            None => {}
+        }
+    }
+
+    /// Applies settings that can change without a restart.
+    fn apply_settings(&mut self) {
+        let (width, height) = (self.settings.width, self.settings.height);
+        if (width as u32, height as u32) != (self.framebuffer.width, self.framebuffer.height) {
+            self.window.set_window_size(width, height);
+            self.framebuffer = Framebuffer::new(width as u32, height as u32);
+            self.framebuffer.set_background_color(Color::BLUE);
+        }
+
+        self.minimap.apply(&self.settings);
+        self.window.set_target_fps(self.settings.target_fps);
+        self.audio.set_volume(self.settings.volume);
+        self.world.player.fov = self.settings.fov_radians();
+    }
+
+    /// Runs as many fixed ticks as the frame time covers. Presses go to the
+    /// first tick only; later ticks in the same frame see the keys as held.
+    fn update_playing(&mut self, input: &InputState) {
+        let mut input = input.clone();
+        input.carry_presses(&self.unticked);
+
+        let steps = self.timestep.advance(self.window.get_frame_time());
+        if steps == 0 {
+            self.unticked = input;
+            return;
+        }
+        self.unticked = InputState::default();
+
+        let held = input.held();
+        for step in 0..steps {
+            let live = if step == 0 { &input } else { &held };
+            let input = match &mut self.playback {
+                Some(playback) => match playback.next_input() {
+                    Some(recorded) => recorded.clone(),
+                    // The recording is over; the world stays frozen where it ended.
+                    None => break,
+                },
+                None => live.clone(),
+            };
+            if let Some(recording) = &mut self.recording {
+                recording.record(&input);
+            }
+
+            for event in self.world.update(TICK, &input) {
+                match event {
+                    WorldEvent::EnemyHit | WorldEvent::EnemyAlerted => self.audio.sfx("assets/goblin.wav"),
+                    WorldEvent::Trap(hazard) => self.audio.sfx(match hazard {
+                        Hazard::Spikes => "assets/spikes.wav",
+                        Hazard::Plate => "assets/rumble.wav",
+                        Hazard::Poison => "assets/poison.wav",
+                    }),
+                    WorldEvent::Triggered(index) => {
+                        if let TriggerAction::Spawn(_) = self.world.triggers[index].action {
+                            self.audio.sfx("assets/goblin.wav");
                         }
                     }
-
-                    if self.player.pos.distance_to(self.princess.pos) < 30.0 {
-                        self.game_state = GameState::Win;
+                    WorldEvent::Message(text) => self.notice = Some((text, MESSAGE_FRAMES)),
+                    WorldEvent::Sound(file) => self.audio.sfx(&file),
+                    // Only the first of the two counts if a tick both wins
+                    // and loses the level.
+                    WorldEvent::GameOver if self.states.state == GameState::Playing => {
+                        self.states.player_died();
+                        self.save_recording();
+                    }
+                    WorldEvent::Won if self.states.state == GameState::Playing => {
+                        let has_next_level = self.complete_level();
+                        // Replays repeat an old run, so they don't get a second entry.
+                        let high_score = self.playback.is_none() && self.high_scores.qualifies(&self.source, self.score().total());
+                        self.states.level_won(has_next_level, high_score);
                         self.audio.sfx("assets/win.wav");
+                        self.save_recording();
                     }
-
-                    let mut mode = "3D";
-                    if self.window.is_key_down(KeyboardKey::KEY_M) {
-                        mode = if mode == "2D" { "3D" } else { "2D" };
+                    WorldEvent::PickedUp(kind) => {
+                        let message = match kind {
+                            PickupKind::Heart => "Extra life!".to_string(),
+                            PickupKind::Item(Item::Key(KeyColor::Iron)) => "Picked up a key".to_string(),
+                            PickupKind::Item(Item::Key(color)) => format!("Picked up a {} key", color.name()),
+                            PickupKind::Item(Item::Coin) => "+1 coin".to_string(),
+                            PickupKind::Item(Item::SpeedPotion) => "Picked up a speed potion".to_string(),
+                            PickupKind::Item(Item::InvisibilityPotion) => "Picked up an invisibility potion".to_string(),
+                        };
+                        self.notice = Some((message, NOTICE_FRAMES));
                     }
-
-                    if mode == "2D" {
-                        render_maze(&mut self.framebuffer, &maze, self.block_size, &self.player);
-                    } else {
-                        render_3d(&mut self.framebuffer, &maze, &self.player, self.block_size, &self.texture_manager);
-                        render_minmap(&mut self.framebuffer, &maze, 20, self.block_size, &self.player, &self.princess, &mut self.enemies, self.minimap_pos);
-                        render_sword(&mut self.framebuffer, &self.texture_manager);
-                        render_enemies(&mut self.framebuffer, &maze, &self.player, &self.texture_manager, &mut self.enemies, &self.princess);
-                        render_lives(&mut self.framebuffer, &self.texture_manager, &self.player);
+                    WorldEvent::ItemUsed(item) => {
+                        let message = if item == Item::SpeedPotion { "Speed boost!" } else { "Invisible!" };
+                        self.notice = Some((message.to_string(), NOTICE_FRAMES));
                     }
-
-                    self.framebuffer.swap_buffers(&mut self.window, &self.raylib_thread);
+                    WorldEvent::DoorOpened(_) => self.notice = Some(("The door opens".to_string(), NOTICE_FRAMES)),
+                    WorldEvent::PlayerHit | WorldEvent::LifeLost | WorldEvent::GameOver | WorldEvent::Won => {}
                 }
             }
+            if self.states.state != GameState::Playing {
+                break;
+            }
+        }
+    }
+
+    fn save_game(&mut self, slot: usize) {
+        let message = match save_world(&slot_path(slot), &self.source, &self.world) {
+            Ok(()) if slot == QUICK_SLOT => "Quick saved".to_string(),
+            Ok(()) => format!("Saved to slot {}", slot),
+            Err(err) => {
+                eprintln!("Failed to save to {}: {}", slot_path(slot), err);
+                "Could not save".to_string()
+            }
+        };
+        self.notice = Some((message, NOTICE_FRAMES));
+        self.read_slots();
+    }
+
+    fn read_slots(&mut self) {
+        self.slots = (0..SLOTS).map(describe_slot).collect();
+    }
+
+    /// Swaps in a saved world, level included. A loaded game no longer
+    /// matches a recording or replay started from the beginning, so both stop.
+    fn load_game(&mut self, slot: usize) {
+        let path = slot_path(slot);
+        match load_world(&path) {
+            Ok((source, level, world)) => {
+                self.source = source;
+                self.level = level;
+                self.world = world;
+                self.world.player.fov = self.settings.fov_radians();
+                self.view_mode = ViewMode::FirstPerson;
+                self.timestep = FixedTimestep::default();
+                self.recording = None;
+                self.playback = None;
+                self.states.start();
+                self.notice = Some(("Loaded".to_string(), NOTICE_FRAMES));
+            }
+            Err(err) => {
+                eprintln!("Failed to load {}: {}", path, err);
+                self.notice = Some(("Could not load that slot".to_string(), NOTICE_FRAMES));
+            }
         }
     }
-}
 
+    fn save_recording(&self) {
+        if let (Some(path), Some(recording)) = (&self.record_path, &self.recording)
+            && let Err(err) = recording.save(path)
+        {
+            eprintln!("Failed to save replay to {}: {}", path, err);
+        }
+    }
+
+    fn render_playing(&mut self, input: &InputState) {
+        self.framebuffer.clear();
+
+        if input.is_pressed(Action::ToggleMap) {
+            self.view_mode = match self.view_mode {
+                ViewMode::FirstPerson => ViewMode::Map,
+                ViewMode::Map => ViewMode::FirstPerson,
+            };
+        }
+
+        if input.is_pressed(Action::MinimapZoom) {
+            self.minimap.cycle_zoom();
+        }
+        if input.is_pressed(Action::MinimapMode) {
+            self.minimap.cycle_mode();
+        }
+        if input.is_pressed(Action::DebugMap) {
+            self.minimap.debug = !self.minimap.debug;
+        }
+
+        let alpha = self.timestep.alpha();
+        let framebuffer = &mut self.framebuffer;
+        let (textures, minimap, settings, view_mode) = (&self.texture_manager, &self.minimap, &self.settings, self.view_mode);
+        self.world.interpolated(alpha, |world| match view_mode {
+            ViewMode::Map => {
+                render_map(framebuffer, &world.maze, &world.automap, minimap, world.block_size, &world.player, &world.enemies, &world.princess, &world.checkpoints);
+                render_lives(framebuffer, textures, &world.player);
+            }
+            ViewMode::FirstPerson => {
+                let minimap = settings.minimap.then_some(minimap);
+                render_first_person(framebuffer, world, textures, minimap, settings.render_scale);
+            }
+        });
+
+        let (width, height) = (self.framebuffer.width, self.framebuffer.height);
+        let notice = self.notice.as_ref().map(|(notice, _)| notice.as_str());
+        let (elapsed, score, player) = (self.world.elapsed, self.score().total(), &self.world.player);
+        let potion_keys = [Action::DrinkSpeed, Action::DrinkInvisibility].map(|action| self.input.bindings.prompt(action, input.using_gamepad));
+        let textures = &self.texture_manager;
+        self.framebuffer.present(&mut self.window, &self.raylib_thread, player.damage_flash(), |d| {
+            render_hud(d, textures, elapsed, score, player, [&potion_keys[0], &potion_keys[1]]);
+            if let Some(notice) = notice {
+                render_notice(d, width, height, notice);
+            }
+        });
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use crate::level::{EnemySpawn, Level};
+use crate::maze::Maze;
+use crate::pathfinding::Cell;
+use crate::patrol::{PatrolMode, PatrolRoute};
+use crate::rng::Rng;
+
+/// Rooms closer than this to the start never get an enemy.
+const SAFE_DISTANCE: usize = 3;
+const ROOMS_PER_ENEMY: usize = 8;
+
+/// Grid cell at the middle of a room. Rooms are laid out like the level
+/// files: each one is a `+--` corner and wall above a row of two open cells.
+fn room_cell((col, row): Cell) -> Cell {
+    (col * 3 + 1, row * 2 + 1)
+}
+
+/// Carves a perfect maze of `cols` x `rows` rooms with a randomized
+/// depth-first search, so every room is reachable by exactly one route.
+fn carve(cols: usize, rows: usize, rng: &mut Rng) -> Maze {
+    let mut maze: Maze = (0..rows * 2 + 1)
+        .map(|row| {
+            (0..cols * 3 + 1)
+                .map(|col| match (row % 2, col % 3) {
+                    (0, 0) => '+',
+                    (0, _) => '-',
+                    (_, 0) => '|',
+                    _ => ' ',
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut visited = vec![vec![false; cols]; rows];
+    let mut stack = vec![(0, 0)];
+    visited[0][0] = true;
+
+    while let Some(&(col, row)) = stack.last() {
+        let mut options = Vec::new();
+        if col > 0 && !visited[row][col - 1] {
+            options.push((col - 1, row));
+        }
+        if col + 1 < cols && !visited[row][col + 1] {
+            options.push((col + 1, row));
+        }
+        if row > 0 && !visited[row - 1][col] {
+            options.push((col, row - 1));
+        }
+        if row + 1 < rows && !visited[row + 1][col] {
+            options.push((col, row + 1));
+        }
+
+        if options.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (next_col, next_row) = options[rng.below(options.len())];
+        if next_row == row {
+            // Knock down the `|` between the two rooms.
+            maze[row * 2 + 1][col.max(next_col) * 3] = ' ';
+        } else {
+            let wall_row = row.max(next_row) * 2;
+            maze[wall_row][col * 3 + 1] = ' ';
+            maze[wall_row][col * 3 + 2] = ' ';
+        }
+
+        visited[next_row][next_col] = true;
+        stack.push((next_col, next_row));
+    }
+
+    maze
+}
+
+/// Distance in rooms from the first room to every other one.
+fn room_distances(maze: &Maze, cols: usize, rows: usize) -> Vec<Vec<usize>> {
+    let mut distances = vec![vec![usize::MAX; cols]; rows];
+    let mut queue = VecDeque::from([(0, 0)]);
+    distances[0][0] = 0;
+
+    while let Some((col, row)) = queue.pop_front() {
+        let (x, y) = room_cell((col, row));
+        let mut neighbours = Vec::new();
+        if col > 0 && maze[y][x - 1] == ' ' {
+            neighbours.push((col - 1, row));
+        }
+        if col + 1 < cols && maze[y][x + 2] == ' ' {
+            neighbours.push((col + 1, row));
+        }
+        if row > 0 && maze[y - 1][x] == ' ' {
+            neighbours.push((col, row - 1));
+        }
+        if row + 1 < rows && maze[y + 1][x] == ' ' {
+            neighbours.push((col, row + 1));
+        }
+
+        for (next_col, next_row) in neighbours {
+            if distances[next_row][next_col] == usize::MAX {
+                distances[next_row][next_col] = distances[row][col] + 1;
+                queue.push_back((next_col, next_row));
+            }
+        }
+    }
+
+    distances
+}
+
+/// Builds a random level from `seed`. The player starts in the top-left room,
+/// the princess waits in the room furthest from it, a checkpoint marks the
+/// halfway point and goblins patrol between random rooms.
+pub fn generate_level(cols: usize, rows: usize, seed: u64) -> Level {
+    let cols = cols.max(2);
+    let rows = rows.max(2);
+    let mut rng = Rng::new(seed);
+
+    let maze = carve(cols, rows, &mut rng);
+    let distances = room_distances(&maze, cols, rows);
+
+    let rooms: Vec<(Cell, usize)> = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (col, row)))
+        .map(|(col, row)| ((col, row), distances[row][col]))
+        .collect();
+    let &(goal, furthest) = rooms.iter().max_by_key(|(_, distance)| *distance).unwrap();
+    let checkpoints = rooms
+        .iter()
+        .find(|(_, distance)| *distance == furthest / 2)
+        .map(|&(room, _)| vec![room_cell(room)])
+        .unwrap_or_default();
+
+    let candidates: Vec<Cell> = rooms
+        .iter()
+        .filter(|&&(room, distance)| distance >= SAFE_DISTANCE && room != goal)
+        .map(|&(room, _)| room)
+        .collect();
+    let enemies = (0..(cols * rows / ROOMS_PER_ENEMY).min(candidates.len()))
+        .map(|i| {
+            let spawn = room_cell(candidates[rng.below(candidates.len())]);
+            let other = room_cell(candidates[rng.below(candidates.len())]);
+            let route = if spawn == other {
+                PatrolRoute::idle()
+            } else {
+                PatrolRoute::new(PatrolMode::PingPong, vec![spawn, other], seed ^ (i as u64 + 1))
+            };
+            EnemySpawn { cell: spawn, route }
+        })
+        .collect();
+
+    Level {
+        maze,
+        player: room_cell((0, 0)),
+        // Face along the top corridor or down, whichever is open.
+        player_angle: if distances[0][1] == 1 { 0.0 } else { PI / 2.0 },
+        princess: room_cell(goal),
+        checkpoints,
+        enemies,
+        pickups: Vec::new(),
+        triggers: Vec::new(),
+        script: None,
+        warnings: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::validate_level;
+
+    #[test]
+    fn same_seed_same_maze() {
+        assert_eq!(generate_level(6, 5, 42).maze, generate_level(6, 5, 42).maze);
+        assert_ne!(generate_level(6, 5, 42).maze, generate_level(6, 5, 43).maze);
+    }
+
+    #[test]
+    fn generated_levels_are_valid() {
+        for seed in 0..20 {
+            let level = generate_level(8, 6, seed);
+            assert_eq!(validate_level(&level), Vec::<String>::new(), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use raylib::math::Vector2;
+
+use crate::maze::{cell_at, Maze};
+use crate::pathfinding::Cell;
+use crate::timestep::ticks;
+
+/// A raised shifting wall. Pressure plates lower it into `SHIFT_FLOOR`.
+pub const SHIFT_WALL: char = '#';
+/// A lowered shifting wall, walkable until a plate raises it again.
+pub const SHIFT_FLOOR: char = '_';
+/// Spikes shoot up once every this many ticks...
+const SPIKE_PERIOD: u32 = ticks(3.0);
+/// ...and stay up this long.
+const SPIKE_RAISED: u32 = ticks(1.0);
+/// Standing in poison hurts once every this many ticks.
+const POISON_PERIOD: u32 = ticks(1.0);
+/// Walls don't rise into cells this close to a body.
+const BODY_RADIUS: f32 = 16.0;
+
+/// Floor cells that do something to whoever stands on them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Hazard {
+    /// Hurts the player and goblins standing on it when the spikes shoot up.
+    Spikes,
+    /// Stepping on it raises or lowers every shifting wall in the maze.
+    Plate,
+    /// Hurts the player and goblins every second they stand in it.
+    Poison,
+}
+
+impl Hazard {
+    pub const ALL: [Hazard; 3] = [Hazard::Spikes, Hazard::Plate, Hazard::Poison];
+
+    /// Maze character in level files.
+    pub fn cell(self) -> char {
+        match self {
+            Hazard::Spikes => '^',
+            Hazard::Plate => '=',
+            Hazard::Poison => '~',
+        }
+    }
+
+    pub fn from_cell(cell: char) -> Option<Hazard> {
+        Hazard::ALL.into_iter().find(|hazard| hazard.cell() == cell)
+    }
+
+    /// Floor texture; spikes have one for each position.
+    pub fn texture_key(self, spikes_raised: bool) -> char {
+        match self {
+            Hazard::Spikes if spikes_raised => 'A',
+            hazard => hazard.cell(),
+        }
+    }
+}
+
+pub fn spikes_raised(tick: u32) -> bool {
+    tick % SPIKE_PERIOD < SPIKE_RAISED
+}
+
+/// Whether a hazard hurts whoever stands on it on this tick.
+pub fn hurts_on(hazard: Hazard, tick: u32) -> bool {
+    match hazard {
+        Hazard::Spikes => tick.is_multiple_of(SPIKE_PERIOD),
+        Hazard::Plate => false,
+        Hazard::Poison => tick.is_multiple_of(POISON_PERIOD),
+    }
+}
+
+/// Cells any of `bodies` overlaps, where a wall can't appear.
+pub fn occupied_cells(bodies: &[Vector2], block_size: usize) -> Vec<Cell> {
+    bodies
+        .iter()
+        .flat_map(|&pos| {
+            [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+                .map(|(dx, dy)| cell_at(pos + Vector2::new(dx, dy) * BODY_RADIUS, block_size))
+        })
+        .collect()
+}
+
+/// Raises every lowered shifting wall and lowers every raised one. Walls
+/// don't rise under any of `bodies`, so nobody gets stuck inside one.
+pub fn shift_walls(maze: &mut Maze, bodies: &[Vector2], block_size: usize) {
+    let occupied = occupied_cells(bodies, block_size);
+
+    for (row_index, row) in maze.iter_mut().enumerate() {
+        for (col_index, cell) in row.iter_mut().enumerate() {
+            *cell = match *cell {
+                SHIFT_WALL => SHIFT_FLOOR,
+                SHIFT_FLOOR if !occupied.contains(&(col_index, row_index)) => SHIFT_WALL,
+                other => other,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walls_shift_around_bodies() {
+        let mut maze: Maze = ["#__#"].iter().map(|row| row.chars().collect()).collect();
+
+        shift_walls(&mut maze, &[Vector2::new(150.0, 50.0)], 100);
+
+        assert_eq!(maze[0].iter().collect::<String>(), "__#_");
+    }
+}
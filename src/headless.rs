@@ -0,0 +1,25 @@
+use raylib::prelude::*;
+
+use crate::framebuffer::Framebuffer;
+use crate::minimap::MinimapConfig;
+use crate::renderer::render_first_person;
+use crate::settings::Settings;
+use crate::textures::TextureManager;
+use crate::world::World;
+
+/// Renders the world as the player sees it straight into an image file,
+/// without opening a window or an audio device.
+pub fn render_to_file(world: &mut World, settings: &Settings, path: &str) {
+    let mut framebuffer = Framebuffer::new(settings.width as u32, settings.height as u32);
+    framebuffer.set_background_color(Color::BLUE);
+    framebuffer.clear();
+
+    let texture_manager = TextureManager::headless();
+    world.player.fov = settings.fov_radians();
+
+    let minimap = MinimapConfig::new(settings);
+    let minimap = settings.minimap.then_some(&minimap);
+    render_first_person(&mut framebuffer, world, &texture_manager, minimap, settings.render_scale);
+
+    framebuffer.color_buffer.export_image(path);
+}
@@ -0,0 +1,806 @@
+use raylib::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    TurnLeft,
+    TurnRight,
+    Sprint,
+    Attack,
+    Use,
+    DrinkSpeed,
+    DrinkInvisibility,
+    ToggleMap,
+    MinimapZoom,
+    MinimapMode,
+    DebugMap,
+    QuickSave,
+    QuickLoad,
+    Pause,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    Confirm,
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 22] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Sprint,
+        Action::Attack,
+        Action::Use,
+        Action::DrinkSpeed,
+        Action::DrinkInvisibility,
+        Action::ToggleMap,
+        Action::MinimapZoom,
+        Action::MinimapMode,
+        Action::DebugMap,
+        Action::QuickSave,
+        Action::QuickLoad,
+        Action::Pause,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::Confirm,
+        Action::Back,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Sprint => "sprint",
+            Action::Attack => "attack",
+            Action::Use => "use",
+            Action::DrinkSpeed => "drink_speed",
+            Action::DrinkInvisibility => "drink_invisibility",
+            Action::ToggleMap => "toggle_map",
+            Action::MinimapZoom => "minimap_zoom",
+            Action::MinimapMode => "minimap_mode",
+            Action::DebugMap => "debug_map",
+            Action::QuickSave => "quick_save",
+            Action::QuickLoad => "quick_load",
+            Action::Pause => "pause",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::Sprint => "Sprint",
+            Action::Attack => "Attack",
+            Action::Use => "Use",
+            Action::DrinkSpeed => "Speed potion",
+            Action::DrinkInvisibility => "Invisibility potion",
+            Action::ToggleMap => "Toggle map",
+            Action::MinimapZoom => "Minimap zoom",
+            Action::MinimapMode => "Minimap mode",
+            Action::DebugMap => "Debug map",
+            Action::QuickSave => "Quick save",
+            Action::QuickLoad => "Quick load",
+            Action::Pause => "Pause",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::MenuLeft => "Menu left",
+            Action::MenuRight => "Menu right",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+
+    /// Without these the player couldn't get through the menus, so they are
+    /// never left unbound.
+    fn is_required(self) -> bool {
+        matches!(self, Action::Confirm | Action::Back | Action::Pause)
+    }
+}
+
+pub const GAMEPAD_BUTTONS: [GamepadButton; 17] = [
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB,
+];
+
+pub const GAMEPAD_AXES: [GamepadAxis; 6] = [
+    GamepadAxis::GAMEPAD_AXIS_LEFT_X,
+    GamepadAxis::GAMEPAD_AXIS_LEFT_Y,
+    GamepadAxis::GAMEPAD_AXIS_RIGHT_X,
+    GamepadAxis::GAMEPAD_AXIS_RIGHT_Y,
+    GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER,
+    GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AxisDirection {
+    Negative,
+    Positive,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(KeyboardKey),
+    Button(GamepadButton),
+    Axis(GamepadAxis, AxisDirection),
+}
+
+impl Binding {
+    /// Name used in `controls.cfg`, e.g. `UP`, `BUTTON_RIGHT_FACE_DOWN` or `AXIS_LEFT_Y-`.
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key).trim_start_matches("KEY_").to_string(),
+            Binding::Button(button) => format!("{:?}", button).trim_start_matches("GAMEPAD_").to_string(),
+            Binding::Axis(axis, direction) => {
+                let sign = match direction {
+                    AxisDirection::Negative => '-',
+                    AxisDirection::Positive => '+',
+                };
+                format!("{}{}", format!("{:?}", axis).trim_start_matches("GAMEPAD_"), sign)
+            }
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Binding> {
+        let name = name.trim().to_uppercase();
+
+        if let Some(axis) = name.strip_prefix("AXIS_") {
+            let (axis, direction) = match axis.strip_suffix('-') {
+                Some(axis) => (axis, AxisDirection::Negative),
+                None => (axis.strip_suffix('+')?, AxisDirection::Positive),
+            };
+            let wanted = format!("GAMEPAD_AXIS_{}", axis);
+            return GAMEPAD_AXES
+                .iter()
+                .find(|candidate| format!("{:?}", candidate) == wanted)
+                .map(|axis| Binding::Axis(*axis, direction));
+        }
+
+        if name.starts_with("BUTTON_") {
+            let wanted = format!("GAMEPAD_{}", name);
+            return GAMEPAD_BUTTONS
+                .iter()
+                .find(|candidate| format!("{:?}", candidate) == wanted)
+                .map(|button| Binding::Button(*button));
+        }
+
+        let wanted = format!("KEY_{}", name);
+        (1..=400)
+            .filter_map(key_from_i32)
+            .find(|key| format!("{:?}", key) == wanted)
+            .map(Binding::Key)
+    }
+
+    pub fn is_gamepad(&self) -> bool {
+        !matches!(self, Binding::Key(_))
+    }
+
+    /// Short label shown in menus and button prompts.
+    pub fn label(&self) -> String {
+        use GamepadAxis::*;
+        use GamepadButton::*;
+
+        match self {
+            Binding::Key(_) => self.name(),
+            Binding::Button(button) => match button {
+                GAMEPAD_BUTTON_LEFT_FACE_UP => "D-Pad Up",
+                GAMEPAD_BUTTON_LEFT_FACE_RIGHT => "D-Pad Right",
+                GAMEPAD_BUTTON_LEFT_FACE_DOWN => "D-Pad Down",
+                GAMEPAD_BUTTON_LEFT_FACE_LEFT => "D-Pad Left",
+                GAMEPAD_BUTTON_RIGHT_FACE_UP => "Y",
+                GAMEPAD_BUTTON_RIGHT_FACE_RIGHT => "B",
+                GAMEPAD_BUTTON_RIGHT_FACE_DOWN => "A",
+                GAMEPAD_BUTTON_RIGHT_FACE_LEFT => "X",
+                GAMEPAD_BUTTON_LEFT_TRIGGER_1 => "LB",
+                GAMEPAD_BUTTON_LEFT_TRIGGER_2 => "LT",
+                GAMEPAD_BUTTON_RIGHT_TRIGGER_1 => "RB",
+                GAMEPAD_BUTTON_RIGHT_TRIGGER_2 => "RT",
+                GAMEPAD_BUTTON_MIDDLE_LEFT => "Back",
+                GAMEPAD_BUTTON_MIDDLE => "Guide",
+                GAMEPAD_BUTTON_MIDDLE_RIGHT => "Start",
+                GAMEPAD_BUTTON_LEFT_THUMB => "LS",
+                GAMEPAD_BUTTON_RIGHT_THUMB => "RS",
+                GAMEPAD_BUTTON_UNKNOWN => "?",
+            }
+            .to_string(),
+            Binding::Axis(axis, direction) => {
+                let negative = *direction == AxisDirection::Negative;
+                match axis {
+                    GAMEPAD_AXIS_LEFT_X => if negative { "L-Stick Left" } else { "L-Stick Right" },
+                    GAMEPAD_AXIS_LEFT_Y => if negative { "L-Stick Up" } else { "L-Stick Down" },
+                    GAMEPAD_AXIS_RIGHT_X => if negative { "R-Stick Left" } else { "R-Stick Right" },
+                    GAMEPAD_AXIS_RIGHT_Y => if negative { "R-Stick Up" } else { "R-Stick Down" },
+                    GAMEPAD_AXIS_LEFT_TRIGGER => "LT",
+                    GAMEPAD_AXIS_RIGHT_TRIGGER => "RT",
+                }
+                .to_string()
+            }
+        }
+    }
+}
+
+pub struct InputBindings {
+    bindings: HashMap<Action, Vec<Binding>>,
+    pub dead_zone: f32,
+}
+
+impl InputBindings {
+    pub fn defaults() -> Self {
+        use AxisDirection::*;
+        use Binding::*;
+        use GamepadAxis::*;
+        use GamepadButton::*;
+        use KeyboardKey::*;
+
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::MoveForward, vec![Key(KEY_UP), Key(KEY_W), Axis(GAMEPAD_AXIS_LEFT_Y, Negative)]);
+        bindings.insert(Action::MoveBackward, vec![Key(KEY_DOWN), Key(KEY_S), Axis(GAMEPAD_AXIS_LEFT_Y, Positive)]);
+        bindings.insert(Action::TurnLeft, vec![Key(KEY_LEFT), Key(KEY_A), Axis(GAMEPAD_AXIS_RIGHT_X, Negative), Axis(GAMEPAD_AXIS_LEFT_X, Negative)]);
+        bindings.insert(Action::TurnRight, vec![Key(KEY_RIGHT), Key(KEY_D), Axis(GAMEPAD_AXIS_RIGHT_X, Positive), Axis(GAMEPAD_AXIS_LEFT_X, Positive)]);
+        bindings.insert(Action::Sprint, vec![Key(KEY_LEFT_SHIFT), Axis(GAMEPAD_AXIS_LEFT_TRIGGER, Positive), Button(GAMEPAD_BUTTON_LEFT_TRIGGER_2)]);
+        bindings.insert(Action::Attack, vec![Key(KEY_SPACE), Axis(GAMEPAD_AXIS_RIGHT_TRIGGER, Positive), Button(GAMEPAD_BUTTON_RIGHT_TRIGGER_2)]);
+        bindings.insert(Action::Use, vec![Key(KEY_E), Button(GAMEPAD_BUTTON_RIGHT_FACE_LEFT)]);
+        bindings.insert(Action::DrinkSpeed, vec![Key(KEY_ONE), Button(GAMEPAD_BUTTON_LEFT_TRIGGER_1)]);
+        bindings.insert(Action::DrinkInvisibility, vec![Key(KEY_TWO), Button(GAMEPAD_BUTTON_RIGHT_TRIGGER_1)]);
+        bindings.insert(Action::ToggleMap, vec![Key(KEY_M), Key(KEY_TAB), Button(GAMEPAD_BUTTON_MIDDLE_LEFT)]);
+        bindings.insert(Action::MinimapZoom, vec![Key(KEY_Z), Button(GAMEPAD_BUTTON_RIGHT_THUMB)]);
+        bindings.insert(Action::MinimapMode, vec![Key(KEY_N), Button(GAMEPAD_BUTTON_LEFT_THUMB)]);
+        bindings.insert(Action::DebugMap, vec![Key(KEY_F3)]);
+        bindings.insert(Action::QuickSave, vec![Key(KEY_F5)]);
+        bindings.insert(Action::QuickLoad, vec![Key(KEY_F9)]);
+        bindings.insert(Action::Pause, vec![Key(KEY_ESCAPE), Key(KEY_P), Button(GAMEPAD_BUTTON_MIDDLE_RIGHT)]);
+        bindings.insert(Action::MenuUp, vec![Key(KEY_UP), Key(KEY_W), Button(GAMEPAD_BUTTON_LEFT_FACE_UP), Axis(GAMEPAD_AXIS_LEFT_Y, Negative)]);
+        bindings.insert(Action::MenuDown, vec![Key(KEY_DOWN), Key(KEY_S), Button(GAMEPAD_BUTTON_LEFT_FACE_DOWN), Axis(GAMEPAD_AXIS_LEFT_Y, Positive)]);
+        bindings.insert(Action::MenuLeft, vec![Key(KEY_LEFT), Key(KEY_A), Button(GAMEPAD_BUTTON_LEFT_FACE_LEFT), Axis(GAMEPAD_AXIS_LEFT_X, Negative)]);
+        bindings.insert(Action::MenuRight, vec![Key(KEY_RIGHT), Key(KEY_D), Button(GAMEPAD_BUTTON_LEFT_FACE_RIGHT), Axis(GAMEPAD_AXIS_LEFT_X, Positive)]);
+        bindings.insert(Action::Confirm, vec![Key(KEY_ENTER), Button(GAMEPAD_BUTTON_RIGHT_FACE_DOWN)]);
+        bindings.insert(Action::Back, vec![Key(KEY_BACKSPACE), Button(GAMEPAD_BUTTON_RIGHT_FACE_RIGHT)]);
+
+        InputBindings { bindings, dead_zone: 0.25 }
+    }
+
+    /// Loads `action = BINDING, BINDING` lines on top of the defaults, so a
+    /// config file only has to list the actions it wants to change.
+    pub fn load(path: &str) -> Self {
+        let mut bindings = InputBindings::defaults();
+        let Ok(contents) = fs::read_to_string(path) else {
+            return bindings;
+        };
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                eprintln!("{}: ignoring malformed line '{}'", path, line);
+                continue;
+            };
+
+            if name.trim() == "dead_zone" {
+                match value.trim().parse::<f32>() {
+                    Ok(dead_zone) => bindings.dead_zone = dead_zone.clamp(0.0, 0.95),
+                    Err(_) => eprintln!("{}: invalid dead_zone '{}'", path, value.trim()),
+                }
+                continue;
+            }
+
+            let Some(action) = Action::from_name(name.trim()) else {
+                eprintln!("{}: unknown action '{}'", path, name.trim());
+                continue;
+            };
+
+            let mut parsed = Vec::new();
+            for binding in value.split(',').filter(|binding| !binding.trim().is_empty()) {
+                match Binding::parse(binding) {
+                    Some(binding) => parsed.push(binding),
+                    None => eprintln!("{}: unknown binding '{}'", path, binding.trim()),
+                }
+            }
+            if parsed.is_empty() && action.is_required() {
+                eprintln!("{}: {} can't be unbound, keeping its defaults", path, name.trim());
+                continue;
+            }
+            bindings.bindings.insert(action, parsed);
+        }
+
+        bindings
+    }
+
+    pub fn save(&self, path: &str) {
+        let mut contents = String::from("# action = binding[, binding...]\n");
+        contents.push_str(&format!("dead_zone = {}\n", self.dead_zone));
+        for action in Action::ALL {
+            let names: Vec<String> = self.bindings(action).iter().map(|binding| binding.name()).collect();
+            contents.push_str(&format!("{} = {}\n", action.name(), names.join(", ")));
+        }
+
+        if let Err(err) = fs::write(path, contents) {
+            eprintln!("Failed to save bindings to {}: {}", path, err);
+        }
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes every binding of `action`. Required actions go back to their
+    /// defaults instead.
+    pub fn clear(&mut self, action: Action) {
+        let bindings = if action.is_required() { InputBindings::defaults().bindings(action).to_vec() } else { Vec::new() };
+        self.bindings.insert(action, bindings);
+    }
+
+    pub fn describe(&self, action: Action) -> String {
+        let labels: Vec<String> = self.bindings(action).iter().map(|binding| binding.label()).collect();
+        if labels.is_empty() {
+            "-".to_string()
+        } else {
+            labels.join(", ")
+        }
+    }
+
+    /// Label of the first binding for the device the player is using, for
+    /// on-screen prompts like "A: select".
+    pub fn prompt(&self, action: Action, gamepad: bool) -> String {
+        self.bindings(action)
+            .iter()
+            .find(|binding| binding.is_gamepad() == gamepad)
+            .or_else(|| self.bindings(action).first())
+            .map(|binding| binding.label())
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+#[derive(Clone)]
+pub struct GamepadState {
+    pub buttons: HashSet<GamepadButton>,
+    pub axes: [f32; 6],
+}
+
+/// Sticks centered and triggers released.
+impl Default for GamepadState {
+    fn default() -> Self {
+        GamepadState { buttons: HashSet::new(), axes: [0.0, 0.0, 0.0, 0.0, -1.0, -1.0] }
+    }
+}
+
+fn is_trigger(axis: GamepadAxis) -> bool {
+    matches!(axis, GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER | GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER)
+}
+
+/// Raw snapshot of the devices for one frame. It can be read from raylib or
+/// built by hand, which is how the tests feed synthetic gamepad input.
+#[derive(Clone, Default)]
+pub struct DeviceState {
+    pub keys: HashSet<KeyboardKey>,
+    pub gamepad: Option<GamepadState>,
+}
+
+impl DeviceState {
+    pub fn read(rl: &RaylibHandle, bindings: &InputBindings, gamepad: i32) -> Self {
+        let mut state = DeviceState::default();
+
+        for action in Action::ALL {
+            for binding in bindings.bindings(action) {
+                if let Binding::Key(key) = binding
+                    && rl.is_key_down(*key)
+                {
+                    state.keys.insert(*key);
+                }
+            }
+        }
+
+        if rl.is_gamepad_available(gamepad) {
+            let mut pad = GamepadState::default();
+            for button in GAMEPAD_BUTTONS {
+                if rl.is_gamepad_button_down(gamepad, button) {
+                    pad.buttons.insert(button);
+                }
+            }
+            for (i, axis) in GAMEPAD_AXES.iter().enumerate() {
+                pad.axes[i] = rl.get_gamepad_axis_movement(gamepad, *axis);
+            }
+            state.gamepad = Some(pad);
+        }
+
+        state
+    }
+
+    /// How strongly a binding is held, from 0.0 to 1.0. Axis travel inside
+    /// the dead-zone is ignored and the rest is rescaled to the full range.
+    pub fn value(&self, binding: &Binding, dead_zone: f32) -> f32 {
+        match binding {
+            Binding::Key(key) => if self.keys.contains(key) { 1.0 } else { 0.0 },
+            Binding::Button(button) => match &self.gamepad {
+                Some(pad) if pad.buttons.contains(button) => 1.0,
+                _ => 0.0,
+            },
+            Binding::Axis(axis, direction) => {
+                let Some(pad) = &self.gamepad else {
+                    return 0.0;
+                };
+                let mut raw = pad.axes[*axis as usize];
+                // Triggers go from -1.0 released to 1.0 pulled all the way.
+                if is_trigger(*axis) {
+                    raw = (raw + 1.0) / 2.0;
+                }
+                let travel = match direction {
+                    AxisDirection::Negative => -raw,
+                    AxisDirection::Positive => raw,
+                };
+                if travel <= dead_zone {
+                    0.0
+                } else {
+                    ((travel - dead_zone) / (1.0 - dead_zone)).min(1.0)
+                }
+            }
+        }
+    }
+}
+
+/// The actions held and newly pressed during one frame. Game logic reads
+/// this instead of querying raylib key codes directly.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct InputState {
+    values: HashMap<Action, f32>,
+    pressed: HashSet<Action>,
+    pub using_gamepad: bool,
+}
+
+impl InputState {
+    pub fn resolve(bindings: &InputBindings, devices: &DeviceState, previous: &InputState) -> Self {
+        let mut state = InputState {
+            using_gamepad: previous.using_gamepad,
+            ..InputState::default()
+        };
+        let mut keyboard_active = false;
+        let mut gamepad_active = false;
+
+        for action in Action::ALL {
+            let mut value: f32 = 0.0;
+            for binding in bindings.bindings(action) {
+                let amount = devices.value(binding, bindings.dead_zone);
+                if amount > 0.0 {
+                    if binding.is_gamepad() {
+                        gamepad_active = true;
+                    } else {
+                        keyboard_active = true;
+                    }
+                }
+                value = value.max(amount);
+            }
+
+            if value > 0.0 {
+                state.values.insert(action, value);
+                if !previous.is_down(action) {
+                    state.pressed.insert(action);
+                }
+            }
+        }
+
+        if gamepad_active {
+            state.using_gamepad = true;
+        } else if keyboard_active {
+            state.using_gamepad = false;
+        }
+
+        state
+    }
+
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.value(action) > 0.0
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// The same held actions without this frame's presses, for the extra
+    /// simulation ticks that run in a single frame.
+    pub fn held(&self) -> InputState {
+        InputState { pressed: HashSet::new(), ..self.clone() }
+    }
+
+    /// Adds presses from an earlier frame that no simulation tick consumed.
+    pub fn carry_presses(&mut self, earlier: &InputState) {
+        self.pressed.extend(&earlier.pressed);
+    }
+
+    /// Held actions as words, `turn_left=0.5` for partial values and a
+    /// trailing `!` on fresh presses, e.g. `move_forward attack!`.
+    pub fn to_line(&self) -> String {
+        let mut words = Vec::new();
+        for action in Action::ALL {
+            let value = self.value(action);
+            if value <= 0.0 {
+                continue;
+            }
+            let mut word = action.name().to_string();
+            if value < 1.0 {
+                word += &format!("={}", value);
+            }
+            if self.is_pressed(action) {
+                word.push('!');
+            }
+            words.push(word);
+        }
+        words.join(" ")
+    }
+
+    pub fn parse_line(line: &str) -> Option<InputState> {
+        let mut state = InputState::default();
+        for word in line.split_whitespace() {
+            let (word, pressed) = match word.strip_suffix('!') {
+                Some(word) => (word, true),
+                None => (word, false),
+            };
+            let (name, value) = match word.split_once('=') {
+                Some((name, value)) => (name, value.parse().ok().filter(|value| *value > 0.0 && *value <= 1.0)?),
+                None => (word, 1.0),
+            };
+            let action = Action::from_name(name)?;
+            state.values.insert(action, value);
+            if pressed {
+                state.pressed.insert(action);
+            }
+        }
+        Some(state)
+    }
+}
+
+pub struct Input {
+    pub bindings: InputBindings,
+    pub gamepad: i32,
+    path: String,
+    state: InputState,
+    devices: DeviceState,
+    previous_devices: DeviceState,
+}
+
+impl Input {
+    pub fn new(path: &str) -> Self {
+        Input {
+            bindings: InputBindings::load(path),
+            gamepad: 0,
+            path: path.to_string(),
+            state: InputState::default(),
+            devices: DeviceState::default(),
+            previous_devices: DeviceState::default(),
+        }
+    }
+
+    pub fn poll(&mut self, rl: &RaylibHandle) -> InputState {
+        let devices = DeviceState::read(rl, &self.bindings, self.gamepad);
+        self.state = InputState::resolve(&self.bindings, &devices, &self.state);
+        self.previous_devices = std::mem::replace(&mut self.devices, devices);
+        self.state.clone()
+    }
+
+    /// The first key, button or stick direction that went down this frame,
+    /// used by the rebinding screen.
+    pub fn capture(&self, rl: &mut RaylibHandle) -> Option<Binding> {
+        if let Some(key) = rl.get_key_pressed() {
+            return Some(Binding::Key(key));
+        }
+
+        let pad = self.devices.gamepad.as_ref()?;
+        let previous = DeviceState {
+            keys: HashSet::new(),
+            gamepad: Some(self.previous_devices.gamepad.clone().unwrap_or_default()),
+        };
+
+        if let Some(button) = pad.buttons.iter().find(|button| previous.value(&Binding::Button(**button), 0.0) == 0.0) {
+            return Some(Binding::Button(*button));
+        }
+
+        for axis in GAMEPAD_AXES {
+            for direction in [AxisDirection::Negative, AxisDirection::Positive] {
+                // Triggers only pull one way.
+                if is_trigger(axis) && direction == AxisDirection::Negative {
+                    continue;
+                }
+
+                let binding = Binding::Axis(axis, direction);
+                if self.devices.value(&binding, 0.5) > 0.0 && previous.value(&binding, 0.5) == 0.0 {
+                    return Some(binding);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn save(&self) {
+        self.bindings.save(&self.path);
+    }
+}
+
+/// Rebinding screen state: which action is highlighted and whether the next
+/// key or button press should be captured as a new binding for it.
+#[derive(Default)]
+pub struct Rebinder {
+    pub selected: usize,
+    pub waiting: bool,
+}
+
+impl Rebinder {
+    /// Returns true when the player leaves the screen.
+    pub fn update(&mut self, rl: &mut RaylibHandle, input: &mut Input, state: &InputState) -> bool {
+        if self.waiting {
+            if let Some(binding) = input.capture(rl) {
+                if !input.bindings.bindings(Action::Back).contains(&binding) {
+                    input.bindings.bind(Action::ALL[self.selected], binding);
+                }
+                self.waiting = false;
+            }
+            return false;
+        }
+
+        if state.is_pressed(Action::MenuUp) {
+            self.selected = (self.selected + Action::ALL.len() - 1) % Action::ALL.len();
+        }
+        if state.is_pressed(Action::MenuDown) {
+            self.selected = (self.selected + 1) % Action::ALL.len();
+        }
+        if state.is_pressed(Action::Confirm) {
+            self.waiting = true;
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_DELETE) {
+            input.bindings.clear(Action::ALL[self.selected]);
+        }
+        if state.is_pressed(Action::Back) {
+            input.save();
+            return true;
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sticks centered and triggers released, as GLFW reports them.
+    const AT_REST: [f32; 6] = [0.0, 0.0, 0.0, 0.0, -1.0, -1.0];
+
+    fn pad(axes: [f32; 6], buttons: &[GamepadButton]) -> DeviceState {
+        DeviceState {
+            keys: HashSet::new(),
+            gamepad: Some(GamepadState {
+                buttons: buttons.iter().copied().collect(),
+                axes,
+            }),
+        }
+    }
+
+    #[test]
+    fn stick_inside_dead_zone_is_ignored() {
+        let bindings = InputBindings::defaults();
+        let devices = pad([0.1, -0.2, 0.0, 0.0, -1.0, -1.0], &[]);
+        let state = InputState::resolve(&bindings, &devices, &InputState::default());
+
+        assert!(!state.is_down(Action::MoveForward));
+        assert!(!state.is_down(Action::TurnRight));
+    }
+
+    #[test]
+    fn stick_outside_dead_zone_is_rescaled() {
+        let bindings = InputBindings::defaults();
+        let devices = pad([0.0, -1.0, 0.625, 0.0, -1.0, -1.0], &[]);
+        let state = InputState::resolve(&bindings, &devices, &InputState::default());
+
+        assert_eq!(state.value(Action::MoveForward), 1.0);
+        assert_eq!(state.value(Action::MoveBackward), 0.0);
+        assert!((state.value(Action::TurnRight) - 0.5).abs() < 1e-5);
+        assert!(state.using_gamepad);
+    }
+
+    #[test]
+    fn trigger_attack_is_pressed_once() {
+        let bindings = InputBindings::defaults();
+        let resting = InputState::resolve(&bindings, &pad(AT_REST, &[]), &InputState::default());
+        let half = InputState::resolve(&bindings, &pad([0.0, 0.0, 0.0, 0.0, -1.0, 0.0], &[]), &resting);
+        let devices = pad([0.0, 0.0, 0.0, 0.0, -1.0, 1.0], &[]);
+
+        let first = InputState::resolve(&bindings, &devices, &resting);
+        let held = InputState::resolve(&bindings, &devices, &first);
+
+        assert!(!resting.is_down(Action::Attack) && !resting.is_down(Action::Sprint));
+        assert!((half.value(Action::Attack) - 1.0 / 3.0).abs() < 1e-5);
+        assert!(first.is_pressed(Action::Attack));
+        assert!(held.is_down(Action::Attack));
+        assert!(!held.is_pressed(Action::Attack));
+    }
+
+    #[test]
+    fn keyboard_use_switches_prompts_back() {
+        let bindings = InputBindings::defaults();
+        let gamepad = InputState::resolve(
+            &bindings,
+            &pad(AT_REST, &[GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN]),
+            &InputState::default(),
+        );
+        assert!(gamepad.is_pressed(Action::Confirm));
+        assert_eq!(bindings.prompt(Action::Confirm, gamepad.using_gamepad), "A");
+
+        let mut keys = DeviceState::default();
+        keys.keys.insert(KeyboardKey::KEY_ENTER);
+        let keyboard = InputState::resolve(&bindings, &keys, &gamepad);
+        assert!(!keyboard.using_gamepad);
+        assert_eq!(bindings.prompt(Action::Confirm, keyboard.using_gamepad), "ENTER");
+    }
+
+    #[test]
+    fn menu_actions_are_never_unbound() {
+        let mut bindings = InputBindings::defaults();
+        bindings.bind(Action::Back, Binding::Key(KeyboardKey::KEY_Q));
+
+        bindings.clear(Action::Back);
+        bindings.clear(Action::Attack);
+
+        assert_eq!(bindings.bindings(Action::Back), InputBindings::defaults().bindings(Action::Back));
+        assert!(bindings.bindings(Action::Attack).is_empty());
+    }
+
+    #[test]
+    fn binding_names_round_trip() {
+        let bindings = [
+            Binding::Key(KeyboardKey::KEY_LEFT_SHIFT),
+            Binding::Button(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+            Binding::Axis(GamepadAxis::GAMEPAD_AXIS_LEFT_Y, AxisDirection::Negative),
+        ];
+        for binding in bindings {
+            assert_eq!(Binding::parse(&binding.name()), Some(binding));
+        }
+    }
+}
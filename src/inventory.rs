@@ -0,0 +1,184 @@
+use raylib::prelude::*;
+
+/// Keys only open doors of their own color. Iron keys and doors are the
+/// plain ones.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyColor {
+    Iron,
+    Red,
+    Green,
+    Blue,
+}
+
+impl KeyColor {
+    pub const ALL: [KeyColor; 4] = [KeyColor::Iron, KeyColor::Red, KeyColor::Green, KeyColor::Blue];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyColor::Iron => "iron",
+            KeyColor::Red => "red",
+            KeyColor::Green => "green",
+            KeyColor::Blue => "blue",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyColor> {
+        KeyColor::ALL.into_iter().find(|color| color.name() == name)
+    }
+
+    /// Maze character of the matching door, which is also its texture key.
+    pub fn door(self) -> char {
+        match self {
+            KeyColor::Iron => 'D',
+            KeyColor::Red => 'R',
+            KeyColor::Green => 'G',
+            KeyColor::Blue => 'B',
+        }
+    }
+
+    /// Color of the door in a maze cell, if the cell is a door.
+    pub fn from_door(cell: char) -> Option<KeyColor> {
+        KeyColor::ALL.into_iter().find(|color| color.door() == cell)
+    }
+
+    /// Multiplied into the silver key texture.
+    pub fn tint(self) -> Color {
+        match self {
+            KeyColor::Iron => Color::WHITE,
+            KeyColor::Red => Color::new(255, 90, 90, 255),
+            KeyColor::Green => Color::new(110, 230, 110, 255),
+            KeyColor::Blue => Color::new(110, 150, 255, 255),
+        }
+    }
+}
+
+/// Something the player carries.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Item {
+    Key(KeyColor),
+    Coin,
+    /// Potions wait in the inventory until drunk with their hotkey.
+    SpeedPotion,
+    InvisibilityPotion,
+}
+
+impl Item {
+    /// `red_key`, `coin`..., as written in saves.
+    pub fn name(self) -> String {
+        match self {
+            Item::Key(KeyColor::Iron) => "key".to_string(),
+            Item::Key(color) => format!("{}_key", color.name()),
+            Item::Coin => "coin".to_string(),
+            Item::SpeedPotion => "speed".to_string(),
+            Item::InvisibilityPotion => "invisibility".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Item> {
+        match name {
+            "key" => Some(Item::Key(KeyColor::Iron)),
+            "coin" => Some(Item::Coin),
+            "speed" => Some(Item::SpeedPotion),
+            "invisibility" => Some(Item::InvisibilityPotion),
+            _ => KeyColor::from_name(name.strip_suffix("_key")?).map(Item::Key),
+        }
+    }
+
+    pub fn texture_key(self) -> char {
+        match self {
+            Item::Key(_) => 'k',
+            Item::Coin => 'c',
+            Item::SpeedPotion => 'f',
+            Item::InvisibilityPotion => 'i',
+        }
+    }
+
+    pub fn tint(self) -> Color {
+        match self {
+            Item::Key(color) => color.tint(),
+            _ => Color::WHITE,
+        }
+    }
+}
+
+/// Items carried and how many of each, in the order they were first picked up.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Inventory {
+    items: Vec<(Item, u32)>,
+}
+
+impl Inventory {
+    pub fn add(&mut self, item: Item) {
+        match self.items.iter_mut().find(|(held, _)| *held == item) {
+            Some((_, count)) => *count += 1,
+            None => self.items.push((item, 1)),
+        }
+    }
+
+    pub fn count(&self, item: Item) -> u32 {
+        self.items.iter().find(|(held, _)| *held == item).map_or(0, |&(_, count)| count)
+    }
+
+    /// Uses up one of `item`. Returns false if there was none.
+    pub fn take(&mut self, item: Item) -> bool {
+        let Some(index) = self.items.iter().position(|(held, _)| *held == item) else {
+            return false;
+        };
+        self.items[index].1 -= 1;
+        if self.items[index].1 == 0 {
+            self.items.remove(index);
+        }
+        true
+    }
+
+    pub fn items(&self) -> &[(Item, u32)] {
+        &self.items
+    }
+
+    /// `name=count` words, for saves.
+    pub fn to_words(&self) -> Vec<String> {
+        self.items.iter().map(|(item, count)| format!("{}={}", item.name(), count)).collect()
+    }
+
+    pub fn parse_words(words: &[&str]) -> Option<Inventory> {
+        let mut inventory = Inventory::default();
+        for word in words {
+            let (name, count) = word.split_once('=')?;
+            inventory.items.push((Item::from_name(name)?, count.parse().ok()?));
+        }
+        Some(inventory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_stack_and_run_out() {
+        let mut inventory = Inventory::default();
+        inventory.add(Item::Key(KeyColor::Red));
+        inventory.add(Item::Coin);
+        inventory.add(Item::Key(KeyColor::Red));
+
+        assert_eq!(inventory.count(Item::Key(KeyColor::Red)), 2);
+        assert!(!inventory.take(Item::Key(KeyColor::Blue)));
+        assert!(inventory.take(Item::Key(KeyColor::Red)));
+        assert!(inventory.take(Item::Key(KeyColor::Red)));
+        assert_eq!(inventory.items(), &[(Item::Coin, 1)]);
+    }
+
+    #[test]
+    fn words_round_trip() {
+        let mut inventory = Inventory::default();
+        for item in [Item::Key(KeyColor::Iron), Item::Key(KeyColor::Green), Item::Coin, Item::Coin, Item::SpeedPotion] {
+            inventory.add(item);
+        }
+
+        let words = inventory.to_words();
+        assert_eq!(words, ["key=1", "green_key=1", "coin=2", "speed=1"]);
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        assert_eq!(Inventory::parse_words(&words), Some(inventory));
+        assert_eq!(Inventory::parse_words(&["rusty_key=1"]), None);
+    }
+}
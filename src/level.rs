@@ -0,0 +1,413 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rhai::AST;
+
+use crate::generator::generate_level;
+use crate::hazard::SHIFT_WALL;
+use crate::inventory::{Item, KeyColor};
+use crate::maze::{is_walkable, Maze};
+use crate::pathfinding::{find_path, Cell};
+use crate::patrol::{PatrolMode, PatrolRoute};
+use crate::pickup::PickupKind;
+use crate::script::{has_function, Script};
+use crate::trigger::{Trigger, TriggerAction};
+
+pub struct EnemySpawn {
+    pub cell: Cell,
+    pub route: PatrolRoute,
+}
+
+/// A level file is the maze grid, a blank line, then one entity per line:
+///
+/// ```text
+/// player 1 1 60
+/// princess 5 5
+/// checkpoint 8 3
+/// enemy 10 1 loop 10,1 13,1 13,4
+/// key 3 5
+/// trigger 4,1-6,2 message The goblins are waking up
+/// ```
+///
+/// Positions are `column row` cells; the player's angle is in degrees. An
+/// enemy may be followed by a patrol mode (`loop`, `pingpong`, `random`) and
+/// its `column,row` waypoints. Pickups (`heart`, `key`, `coin`, `speed`,
+/// `invisibility`) are placed the same way; keys may name a color first, as
+/// in `key red 3 5`. In the grid, `D` is a door that takes an iron key and
+/// `R`, `G`, `B` take red, green and blue ones. Hazards are floor cells:
+/// `^` spikes, `~` poison and `=` pressure plates, which swap every raised
+/// shifting wall `#` with every lowered one `_`.
+///
+/// A trigger covers one `column,row` cell or a `column,row-column,row`
+/// rectangle, optionally preceded by `repeat`, and fires when the player
+/// walks in: `message TEXT`, `open COL,ROW` (a door), `spawn COL,ROW...`
+/// (guarding goblins), `sound FILE`, `music FILE`, `end` (wins the level) or
+/// `call FUNCTION` of the level's Rhai script, given as `script FILE`.
+/// Sound, music and script files are relative to the level file.
+/// Everything after a `#` on an entity line is a comment, except in trigger
+/// messages.
+pub struct Level {
+    pub maze: Maze,
+    pub player: Cell,
+    pub player_angle: f32,
+    pub princess: Cell,
+    /// Cells that become the respawn point once the player walks over them.
+    pub checkpoints: Vec<Cell>,
+    pub enemies: Vec<EnemySpawn>,
+    pub pickups: Vec<(PickupKind, Cell)>,
+    pub triggers: Vec<Trigger>,
+    /// The level's compiled script, if it has one that loaded.
+    pub script: Option<AST>,
+    /// Lines that were skipped while parsing.
+    pub warnings: Vec<String>,
+}
+
+fn parse_cell(col: &str, row: &str) -> Option<Cell> {
+    Some((col.parse().ok()?, row.parse().ok()?))
+}
+
+fn parse_waypoint(text: &str) -> Option<Cell> {
+    let (col, row) = text.split_once(',')?;
+    parse_cell(col.trim(), row.trim())
+}
+
+/// Where a level comes from, so it can be built again later, e.g. to play
+/// back a replay.
+#[derive(Clone, PartialEq, Debug)]
+pub enum LevelSource {
+    File(String),
+    Generated { cols: usize, rows: usize, seed: u64 },
+}
+
+impl LevelSource {
+    pub fn load(&self) -> io::Result<Level> {
+        match self {
+            LevelSource::File(path) => load_level(path),
+            LevelSource::Generated { cols, rows, seed } => Ok(generate_level(*cols, *rows, *seed)),
+        }
+    }
+
+    /// `file PATH` or `maze COLSxROWS SEED`, as stored in replay files.
+    pub fn to_line(&self) -> String {
+        match self {
+            LevelSource::File(path) => format!("file {}", path),
+            LevelSource::Generated { cols, rows, seed } => format!("maze {}x{} {}", cols, rows, seed),
+        }
+    }
+
+    pub fn parse_line(line: &str) -> Option<LevelSource> {
+        match line.split_once(' ')? {
+            ("file", path) => Some(LevelSource::File(path.to_string())),
+            ("maze", rest) => {
+                let (size, seed) = rest.split_once(' ')?;
+                let (cols, rows) = size.split_once('x')?;
+                Some(LevelSource::Generated { cols: cols.parse().ok()?, rows: rows.parse().ok()?, seed: seed.parse().ok()? })
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn load_level(filename: &str) -> io::Result<Level> {
+    let contents = fs::read_to_string(filename)?;
+    Ok(parse_level(filename, &contents))
+}
+
+/// Parses level text. `name` is the level's file: warnings about bad lines
+/// mention it, and its script is looked for next to it.
+pub fn parse_level(name: &str, contents: &str) -> Level {
+    let dir = Path::new(name).parent().unwrap_or(Path::new(""));
+    let mut lines = contents.lines();
+
+    let maze: Maze = lines
+        .by_ref()
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| line.chars().collect())
+        .collect();
+
+    let mut level = Level {
+        maze,
+        player: (1, 1),
+        player_angle: PI / 3.0,
+        princess: (1, 1),
+        checkpoints: Vec::new(),
+        enemies: Vec::new(),
+        pickups: Vec::new(),
+        triggers: Vec::new(),
+        script: None,
+        warnings: Vec::new(),
+    };
+
+    for line in lines {
+        let line = strip_comment(line);
+        if line.is_empty() {
+            continue;
+        }
+
+        if parse_entity(&mut level, line, dir).is_none() {
+            level.warnings.push(format!("{}: ignoring invalid entity '{}'", name, line));
+        }
+    }
+
+    level
+}
+
+/// Cuts the comment off an entity line. Trigger messages run to the end of
+/// the line, since their text may contain a `#`.
+fn strip_comment(line: &str) -> &str {
+    let words: Vec<&str> = line.split_whitespace().take(4).collect();
+    if let ["trigger", "repeat", _, "message", ..] | ["trigger", _, "message", ..] = words.as_slice() {
+        return line.trim();
+    }
+    line.split('#').next().unwrap_or("").trim()
+}
+
+/// Parses a `trigger [repeat] AREA ACTION ARGS...` line.
+fn parse_trigger(maze: &Maze, line: &str, dir: &Path) -> Option<Trigger> {
+    let words: Vec<&str> = line.split_whitespace().skip(1).collect();
+    let (repeat, words) = match words.as_slice() {
+        ["repeat", rest @ ..] => (true, rest),
+        words => (false, words),
+    };
+    let [area, action, args @ ..] = words else {
+        return None;
+    };
+    let (first, last) = match area.split_once('-') {
+        Some((first, last)) => (parse_waypoint(first)?, parse_waypoint(last)?),
+        None => (parse_waypoint(area)?, parse_waypoint(area)?),
+    };
+    if first.0 > last.0 || first.1 > last.1 || maze.get(last.1).is_none_or(|row| last.0 >= row.len()) {
+        return None;
+    }
+
+    let action = match (*action, args) {
+        // The text runs to the end of the line as written, spacing included.
+        ("message", [_, ..]) => TriggerAction::Message(line.split_once("message")?.1.trim().to_string()),
+        ("open", [cell]) => {
+            let (col, row) = parse_waypoint(cell)?;
+            KeyColor::from_door(*maze.get(row)?.get(col)?)?;
+            TriggerAction::OpenDoor((col, row))
+        }
+        ("spawn", [_, ..]) => {
+            let cells = args.iter().map(|text| parse_waypoint(text)).collect::<Option<Vec<_>>>()?;
+            if !cells.iter().all(|&cell| is_open(maze, cell)) {
+                return None;
+            }
+            TriggerAction::Spawn(cells)
+        }
+        ("sound", [file]) => TriggerAction::Sound(dir.join(file).to_string_lossy().into_owned()),
+        ("music", [file]) => TriggerAction::Music(dir.join(file).to_string_lossy().into_owned()),
+        ("end", []) => TriggerAction::EndLevel,
+        ("call", [function]) => TriggerAction::Call(function.to_string()),
+        _ => return None,
+    };
+    Some(Trigger { area: (first, last), repeat, action })
+}
+
+fn is_open(maze: &Maze, (col, row): Cell) -> bool {
+    maze.get(row).and_then(|r| r.get(col)).is_some_and(|&cell| is_walkable(cell))
+}
+
+/// Applies one entity line to the level. Anything placed inside a wall is
+/// rejected along with malformed lines.
+fn parse_entity(level: &mut Level, line: &str, dir: &Path) -> Option<()> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["player", col, row, rest @ ..] => {
+            level.player = parse_cell(col, row).filter(|&cell| is_open(&level.maze, cell))?;
+            if let [angle] = rest {
+                level.player_angle = angle.parse::<f32>().ok()?.to_radians();
+            }
+        }
+        ["princess", col, row] => level.princess = parse_cell(col, row).filter(|&cell| is_open(&level.maze, cell))?,
+        ["checkpoint", col, row] => {
+            let cell = parse_cell(col, row).filter(|&cell| is_open(&level.maze, cell))?;
+            level.checkpoints.push(cell);
+        }
+        ["enemy", col, row, rest @ ..] => {
+            let cell = parse_cell(col, row).filter(|&cell| is_open(&level.maze, cell))?;
+            let route = match rest {
+                [] => PatrolRoute::idle(),
+                [mode, waypoints @ ..] => {
+                    let mode = PatrolMode::from_name(mode)?;
+                    let waypoints = waypoints.iter().map(|text| parse_waypoint(text)).collect::<Option<Vec<_>>>()?;
+                    if !waypoints.iter().all(|&waypoint| is_open(&level.maze, waypoint)) {
+                        return None;
+                    }
+                    PatrolRoute::new(mode, waypoints, level.enemies.len() as u64 + 1)
+                }
+            };
+            level.enemies.push(EnemySpawn { cell, route });
+        }
+        ["key", color, col, row] => {
+            let cell = parse_cell(col, row).filter(|&cell| is_open(&level.maze, cell))?;
+            let color = KeyColor::from_name(color)?;
+            level.pickups.push((PickupKind::Item(Item::Key(color)), cell));
+        }
+        [name, col, row] if PickupKind::from_name(name).is_some() => {
+            let cell = parse_cell(col, row).filter(|&cell| is_open(&level.maze, cell))?;
+            level.pickups.push((PickupKind::from_name(name)?, cell));
+        }
+        ["script", file] => match Script::load(&dir.join(file).to_string_lossy()) {
+            Ok(script) => level.script = Some(script),
+            Err(err) => level.warnings.push(format!("script {}", err)),
+        },
+        ["trigger", ..] => {
+            let trigger = parse_trigger(&level.maze, line, dir)?;
+            level.triggers.push(trigger);
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// Checks that a level is playable: the maze is closed, there is a key of
+/// the right color for every door no trigger opens, the script compiles and
+/// has every function triggers call, trigger sounds exist, and the princess,
+/// checkpoints, pickups, triggers and every enemy post can be reached with
+/// the doors open and the shifting walls down. Returns one message per
+/// problem found, including lines skipped while parsing.
+pub fn validate_level(level: &Level) -> Vec<String> {
+    let mut problems = level.warnings.clone();
+    for color in KeyColor::ALL {
+        let opened_by_triggers = level
+            .triggers
+            .iter()
+            .filter(|trigger| matches!(trigger.action, TriggerAction::OpenDoor((col, row)) if level.maze[row][col] == color.door()))
+            .count();
+        let doors = level.maze.iter().flatten().filter(|&&cell| cell == color.door()).count().saturating_sub(opened_by_triggers);
+        let keys = level.pickups.iter().filter(|(kind, _)| *kind == PickupKind::Item(Item::Key(color))).count();
+        if keys < doors {
+            problems.push(format!("{} {} doors but only {} keys", doors, color.name(), keys));
+        }
+    }
+    for trigger in &level.triggers {
+        if let TriggerAction::Call(function) = &trigger.action
+            && !level.script.as_ref().is_some_and(|script| has_function(script, function))
+        {
+            problems.push(format!("trigger calls {}, which the script doesn't define", function));
+        }
+        if let TriggerAction::Sound(file) | TriggerAction::Music(file) = &trigger.action
+            && !Path::new(file).exists()
+        {
+            problems.push(format!("trigger sound {} not found", file));
+        }
+    }
+    let maze: &Maze = &level
+        .maze
+        .iter()
+        .map(|row| row.iter().map(|&cell| if KeyColor::from_door(cell).is_some() || cell == SHIFT_WALL { ' ' } else { cell }).collect())
+        .collect();
+
+    if maze.is_empty() {
+        problems.push("the maze is empty".to_string());
+        return problems;
+    }
+
+    let width = maze[0].len();
+    if maze.iter().any(|row| row.len() != width) {
+        problems.push("maze rows have different lengths".to_string());
+    }
+
+    let last_row = maze.len() - 1;
+    for (row_index, row) in maze.iter().enumerate() {
+        for (col_index, &cell) in row.iter().enumerate() {
+            let border = row_index == 0 || row_index == last_row || col_index == 0 || col_index == row.len() - 1;
+            if border && is_walkable(cell) {
+                problems.push(format!("opening in the outer wall at {},{}", col_index, row_index));
+            }
+        }
+    }
+
+    if !is_open(&level.maze, level.player) {
+        problems.push(format!("player starts inside a wall at {:?}", level.player));
+        return problems;
+    }
+
+    let reachable = |goal: Cell| find_path(maze, level.player, goal).is_some();
+    if !reachable(level.princess) {
+        problems.push(format!("princess at {:?} can't be reached", level.princess));
+    }
+    for &checkpoint in &level.checkpoints {
+        if !reachable(checkpoint) {
+            problems.push(format!("checkpoint at {:?} can't be reached", checkpoint));
+        }
+    }
+    for &(kind, cell) in &level.pickups {
+        if !reachable(cell) {
+            problems.push(format!("{} at {:?} can't be reached", kind.name(), cell));
+        }
+    }
+    for trigger in &level.triggers {
+        if !trigger.cells().any(reachable) {
+            problems.push(format!("trigger at {:?} can't be reached", trigger.area.0));
+        }
+    }
+    for enemy in &level.enemies {
+        for &waypoint in &enemy.route.waypoints {
+            if find_path(maze, enemy.cell, waypoint).is_none() {
+                problems.push(format!("enemy at {:?} can't reach waypoint {:?}", enemy.cell, waypoint));
+            }
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_level_is_valid() {
+        let level = load_level("levels/level1.txt").unwrap();
+        assert_eq!(validate_level(&level), Vec::<String>::new());
+    }
+
+    #[test]
+    fn scripts_are_found_next_to_the_level() {
+        let level = load_level("levels/level3.txt").unwrap();
+        assert!(level.script.is_some());
+        assert_eq!(level.warnings, Vec::<String>::new());
+    }
+
+    #[test]
+    fn unreachable_princess_and_bad_lines_are_reported() {
+        let level = parse_level("test", "+-+-+\n| | |\n+-+-+\n\nplayer 1 1\nprincess 3 1\nenemy 9 9\n");
+        let problems = validate_level(&level);
+
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("enemy 9 9"));
+        assert!(problems[1].contains("princess"));
+    }
+
+    #[test]
+    fn triggers_need_a_real_area_and_target() {
+        let text = "+----+\n|  D |\n+----+\n\nplayer 1 1\nprincess 4 1\n\
+            trigger 1,1-2,1 message Room  #2\ntrigger repeat 2,1 open 3,1 # no key needed\n\
+            trigger 2,1 open 1,1\ntrigger 2,1-1,1 end\ntrigger 9,9 end\n";
+        let level = parse_level("test", text);
+
+        assert_eq!(level.triggers.len(), 2);
+        assert_eq!(level.triggers[0].action, TriggerAction::Message("Room  #2".to_string()));
+        assert_eq!(level.triggers[1].area, ((2, 1), (2, 1)));
+        assert!(level.triggers[1].repeat);
+        assert_eq!(level.warnings.len(), 3);
+        // The trigger opens the only door, so no key is needed.
+        assert_eq!(validate_level(&level), level.warnings);
+    }
+
+    #[test]
+    fn trigger_files_are_relative_to_the_level() {
+        let text = "+--+\n|  |\n+--+\n\nplayer 1 1\nprincess 2 1\ntrigger 1,1 sound ../assets/missing.wav\n";
+        let level = parse_level("levels/test.txt", text);
+
+        let sound = Path::new("levels").join("../assets/missing.wav").to_string_lossy().into_owned();
+        assert_eq!(level.triggers[0].action, TriggerAction::Sound(sound.clone()));
+        assert_eq!(validate_level(&level), vec![format!("trigger sound {} not found", sound)]);
+    }
+}
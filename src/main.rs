@@ -6,16 +6,152 @@ mod textures;
 mod sprites;
 mod renderer;
 mod game;
+mod state;
+mod world;
 mod audio;
+mod enemy;
+mod combat;
+mod pathfinding;
+mod patrol;
+mod level;
+mod rng;
+mod input;
+mod minimap;
+mod settings;
+mod cli;
+mod generator;
+mod headless;
+mod timestep;
+mod replay;
+mod save;
+mod campaign;
+mod score;
+mod pickup;
+mod inventory;
+mod hazard;
+mod trigger;
+mod script;
 
+use std::env;
+use std::process;
+
+use campaign::{Campaign, MANIFEST};
+use cli::{parse_args, USAGE};
 use game::Game;
+use level::{validate_level, LevelSource};
+use replay::Replay;
+use settings::Settings;
+use world::{World, WorldEvent};
 
 fn main() {
-    let window_width = 1300;
-    let window_height = 900;
-    let block_size = 100;
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let replay = options.replay.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load replay {}: {}", path, err);
+            process::exit(1);
+        })
+    });
+    let campaign = Campaign::load(MANIFEST).unwrap_or_else(|err| {
+        eprintln!("Failed to load campaign {}: {}", MANIFEST, err);
+        Campaign::default()
+    });
+    let custom_level = match &replay {
+        Some(replay) => Some(replay.level.clone()),
+        None => options.level_source(),
+    };
+
+    if options.validate {
+        let sources = match &custom_level {
+            Some(source) => vec![source.clone()],
+            None => campaign.levels.iter().map(|level| LevelSource::File(level.file.clone())).collect(),
+        };
+        let mut ok = !sources.is_empty();
+        for source in &sources {
+            ok &= validate(source);
+        }
+        if !ok {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let Some(source) = custom_level.or_else(|| campaign.levels.first().map(|level| LevelSource::File(level.file.clone()))) else {
+        eprintln!("No level to play: {} lists no levels", MANIFEST);
+        process::exit(1);
+    };
+    let level = source.load().unwrap_or_else(|err| {
+        eprintln!("Failed to load level ({}): {}", source.to_line(), err);
+        process::exit(1);
+    });
+    for warning in &level.warnings {
+        eprintln!("{}", warning);
+    }
 
-    let mut game = Game::new(window_width, window_height, block_size);
+    let mut settings = Settings::load(&Settings::path());
+    if let Some((width, height)) = options.size {
+        settings.width = width;
+        settings.height = height;
+    }
+    if let Some(scale) = options.render_scale {
+        settings.render_scale = scale;
+    }
+
+    if let Some(path) = &options.headless {
+        let mut world = match &replay {
+            Some(replay) => {
+                let (world, events) = replay.simulate(&level);
+                let outcome = match events.last() {
+                    Some(WorldEvent::Won) => "won",
+                    Some(WorldEvent::GameOver) => "game over",
+                    _ => "input ran out",
+                };
+                println!(
+                    "{} after {:.2}s at ({:.1}, {:.1}), {} lives, {} health",
+                    outcome, world.elapsed, world.player.pos.x, world.player.pos.y, world.player.lives, world.player.health
+                );
+                world
+            }
+            None => World::new(&level, options.block_size, 0),
+        };
+        headless::render_to_file(&mut world, &settings, path);
+        return;
+    }
+
+    let mut game = Game::new(settings, level, source, campaign, &options, replay);
     game.run();
 }
 
+/// Prints the problems with one level, then whether it passed.
+fn validate(source: &LevelSource) -> bool {
+    let name = match source {
+        LevelSource::File(path) => path.clone(),
+        LevelSource::Generated { seed, .. } => format!("maze from seed {}", seed),
+    };
+    let level = match source.load() {
+        Ok(level) => level,
+        Err(err) => {
+            println!("{}: {}", name, err);
+            return false;
+        }
+    };
+
+    let problems = validate_level(&level);
+    for problem in &problems {
+        println!("{}: {}", name, problem);
+    }
+    if problems.is_empty() {
+        println!("{}: ok", name);
+    }
+    problems.is_empty()
+}
@@ -1,16 +1,8 @@
-use std::{fs::File, io::{BufRead, BufReader}};
+use raylib::math::Vector2;
 
-pub type Maze = Vec<Vec<char>>;
-
-pub fn load_maze(filename: &str) -> Maze {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
+use crate::hazard::{Hazard, SHIFT_FLOOR};
 
-    reader
-        .lines()
-        .map(|line| line.unwrap().chars().collect())
-        .collect()
-}
+pub type Maze = Vec<Vec<char>>;
 
 pub fn get_cell(maze: &Maze, x: f32, y: f32, block_size: usize) -> Option<char> {
     let i = x as usize / block_size;
@@ -18,3 +10,45 @@ pub fn get_cell(maze: &Maze, x: f32, y: f32, block_size: usize) -> Option<char>
 
     maze.get(j).and_then(|row| row.get(i)).copied()
 }
+
+/// Open floor, hazards included.
+pub fn is_walkable(cell: char) -> bool {
+    cell == ' ' || cell == SHIFT_FLOOR || Hazard::from_cell(cell).is_some()
+}
+
+pub fn cell_at(pos: Vector2, block_size: usize) -> (usize, usize) {
+    (pos.x.max(0.0) as usize / block_size, pos.y.max(0.0) as usize / block_size)
+}
+
+pub fn cell_center((col, row): (usize, usize), block_size: usize) -> Vector2 {
+    let half = block_size as f32 / 2.0;
+    Vector2::new((col * block_size) as f32 + half, (row * block_size) as f32 + half)
+}
+
+fn is_clear(maze: &Maze, pos: Vector2, radius: f32, block_size: usize) -> bool {
+    [(-radius, -radius), (radius, -radius), (-radius, radius), (radius, radius)]
+        .iter()
+        .all(|(dx, dy)| {
+            let x = pos.x + dx;
+            let y = pos.y + dy;
+            x >= 0.0 && y >= 0.0 && get_cell(maze, x, y, block_size).is_some_and(is_walkable)
+        })
+}
+
+/// Moves a body of the given radius by `delta`, resolving each axis on its
+/// own so it slides along walls instead of stopping dead.
+pub fn slide(maze: &Maze, pos: Vector2, delta: Vector2, radius: f32, block_size: usize) -> Vector2 {
+    let mut result = pos;
+
+    let moved_x = Vector2::new(result.x + delta.x, result.y);
+    if is_clear(maze, moved_x, radius, block_size) {
+        result = moved_x;
+    }
+
+    let moved_y = Vector2::new(result.x, result.y + delta.y);
+    if is_clear(maze, moved_y, radius, block_size) {
+        result = moved_y;
+    }
+
+    result
+}
@@ -0,0 +1,216 @@
+use raylib::prelude::*;
+use std::f32::consts::PI;
+
+use crate::caster::cast_ray_from;
+use crate::maze::Maze;
+use crate::settings::Settings;
+
+/// The map shows what a player with the default field of view would see, so
+/// exploring plays out the same whatever the settings or frame rate.
+const VIEW_CONE: f32 = PI / 3.0;
+const VIEW_RAYS: u32 = 64;
+
+/// Remembers which maze cells the player has already seen, so the maps
+/// only show explored parts of the level.
+pub struct Automap {
+    seen: Vec<Vec<bool>>,
+}
+
+impl Automap {
+    pub fn new(maze: &Maze) -> Self {
+        Automap {
+            seen: maze.iter().map(|row| vec![false; row.len()]).collect(),
+        }
+    }
+
+    /// One line per maze row, `1` for explored cells, for save files.
+    pub fn to_lines(&self) -> Vec<String> {
+        self.seen
+            .iter()
+            .map(|row| row.iter().map(|&seen| if seen { '1' } else { '0' }).collect())
+            .collect()
+    }
+
+    pub fn reveal(&mut self, col: usize, row: usize) {
+        if let Some(cell) = self.seen.get_mut(row).and_then(|row| row.get_mut(col)) {
+            *cell = true;
+        }
+    }
+
+    /// Marks every cell a view ray crosses, including the wall it stops at.
+    pub fn reveal_ray(&mut self, origin: Vector2, a: f32, distance: f32, block_size: usize) {
+        let step = (block_size as f32 / 8.0).max(1.0);
+        let mut d = 0.0;
+
+        while d <= distance + step {
+            let x = origin.x + d * a.cos();
+            let y = origin.y + d * a.sin();
+            if x >= 0.0 && y >= 0.0 {
+                self.reveal(x as usize / block_size, y as usize / block_size);
+            }
+            d += step;
+        }
+    }
+
+    /// Marks everything in view from `origin` looking along `a`.
+    pub fn reveal_view(&mut self, maze: &Maze, origin: Vector2, a: f32, block_size: usize) {
+        for i in 0..=VIEW_RAYS {
+            let ray_a = a - VIEW_CONE / 2.0 + VIEW_CONE * i as f32 / VIEW_RAYS as f32;
+            let distance = cast_ray_from(maze, origin, ray_a, block_size).distance;
+            self.reveal_ray(origin, ray_a, distance, block_size);
+        }
+    }
+
+    pub fn is_seen(&self, col: usize, row: usize) -> bool {
+        self.seen.get(row).and_then(|row| row.get(col)).copied().unwrap_or(false)
+    }
+
+    pub fn is_seen_at(&self, pos: Vector2, block_size: usize) -> bool {
+        pos.x >= 0.0 && pos.y >= 0.0 && self.is_seen(pos.x as usize / block_size, pos.y as usize / block_size)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MinimapShape {
+    Square,
+    Circle,
+}
+
+/// Window corner the minimap sits in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl MinimapCorner {
+    pub const ALL: [MinimapCorner; 4] = [
+        MinimapCorner::TopLeft,
+        MinimapCorner::TopRight,
+        MinimapCorner::BottomLeft,
+        MinimapCorner::BottomRight,
+    ];
+
+    /// Name used in the settings file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MinimapCorner::TopLeft => "top_left",
+            MinimapCorner::TopRight => "top_right",
+            MinimapCorner::BottomLeft => "bottom_left",
+            MinimapCorner::BottomRight => "bottom_right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MinimapCorner> {
+        MinimapCorner::ALL.iter().copied().find(|corner| corner.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MinimapCorner::TopLeft => "Top left",
+            MinimapCorner::TopRight => "Top right",
+            MinimapCorner::BottomLeft => "Bottom left",
+            MinimapCorner::BottomRight => "Bottom right",
+        }
+    }
+
+    /// Top-left pixel of a `size` pixel minimap in this corner of the window.
+    pub fn position(&self, window_width: u32, window_height: u32, size: u32) -> Vector2 {
+        let margin = 10.0;
+        let right = (window_width as f32 - size as f32 - margin).max(0.0);
+        let bottom = (window_height as f32 - size as f32 - margin).max(0.0);
+
+        match self {
+            MinimapCorner::TopLeft => Vector2::new(margin, margin),
+            MinimapCorner::TopRight => Vector2::new(right, margin),
+            MinimapCorner::BottomLeft => Vector2::new(margin, bottom),
+            MinimapCorner::BottomRight => Vector2::new(right, bottom),
+        }
+    }
+}
+
+/// Pixels per maze cell for each minimap zoom step.
+pub const ZOOM_LEVELS: [f32; 3] = [10.0, 20.0, 40.0];
+
+/// A player-centered minimap viewport. `pos` is its top-left corner and
+/// `size` its width and height in pixels; anything outside is clipped.
+pub struct MinimapConfig {
+    pub pos: Vector2,
+    pub size: u32,
+    pub shape: MinimapShape,
+    pub rotate: bool,
+    pub zoom: usize,
+    pub show_enemies: bool,
+    /// Shows the whole maze, every enemy and their patrol routes on the map.
+    pub debug: bool,
+}
+
+impl MinimapConfig {
+    pub fn new(settings: &Settings) -> Self {
+        let mut config = MinimapConfig {
+            pos: Vector2::zero(),
+            size: 0,
+            shape: MinimapShape::Circle,
+            rotate: true,
+            zoom: 1,
+            show_enemies: false,
+            debug: false,
+        };
+        config.apply(settings);
+        config
+    }
+
+    /// Takes the corner, size and enemy toggle from the settings, keeping
+    /// the current zoom and mode.
+    pub fn apply(&mut self, settings: &Settings) {
+        self.size = settings.minimap_size;
+        self.pos = settings.minimap_corner.position(settings.width as u32, settings.height as u32, self.size);
+        self.show_enemies = settings.minimap_enemies;
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        ZOOM_LEVELS[self.zoom.min(ZOOM_LEVELS.len() - 1)]
+    }
+
+    pub fn cycle_zoom(&mut self) {
+        self.zoom = (self.zoom + 1) % ZOOM_LEVELS.len();
+    }
+
+    /// Steps through rotating/fixed for each shape.
+    pub fn cycle_mode(&mut self) {
+        if self.rotate {
+            self.rotate = false;
+        } else {
+            self.rotate = true;
+            self.shape = match self.shape {
+                MinimapShape::Circle => MinimapShape::Square,
+                MinimapShape::Square => MinimapShape::Circle,
+            };
+        }
+    }
+
+    pub fn center(&self) -> Vector2 {
+        self.pos + Vector2::new(self.size as f32 / 2.0, self.size as f32 / 2.0)
+    }
+
+    /// Whether a point relative to the center falls inside the viewport.
+    pub fn contains(&self, offset: Vector2) -> bool {
+        let half = self.size as f32 / 2.0;
+        match self.shape {
+            MinimapShape::Square => offset.x.abs() <= half && offset.y.abs() <= half,
+            MinimapShape::Circle => offset.length() <= half,
+        }
+    }
+
+    /// Rotation applied to world offsets so the player's heading points up
+    /// when `rotate` is on.
+    pub fn rotation(&self, player_a: f32) -> f32 {
+        if self.rotate {
+            -(player_a + PI / 2.0)
+        } else {
+            0.0
+        }
+    }
+}
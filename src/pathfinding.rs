@@ -0,0 +1,67 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::maze::{is_walkable, Maze};
+
+/// A maze cell as `(column, row)`.
+pub type Cell = (usize, usize);
+
+pub fn heuristic(a: Cell, b: Cell) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+fn neighbours(maze: &Maze, (col, row): Cell) -> impl Iterator<Item = Cell> + '_ {
+    let candidates = [
+        (col.wrapping_sub(1), row),
+        (col + 1, row),
+        (col, row.wrapping_sub(1)),
+        (col, row + 1),
+    ];
+    candidates.into_iter().filter(move |&(c, r)| {
+        maze.get(r).and_then(|row| row.get(c)).is_some_and(|&cell| is_walkable(cell))
+    })
+}
+
+/// A* over the walkable cells of the maze with 4-way movement. The returned
+/// path excludes `start` and ends at `goal`; it is empty when they are the
+/// same cell and `None` when the goal can't be reached.
+pub fn find_path(maze: &Maze, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+    let mut cost: HashMap<Cell, usize> = HashMap::new();
+
+    open.push(Reverse((heuristic(start, goal), start)));
+    cost.insert(start, 0);
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut cell = current;
+            while let Some(&previous) = came_from.get(&cell) {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                cell = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let current_cost = cost[&current];
+        for next in neighbours(maze, current) {
+            let next_cost = current_cost + 1;
+            if cost.get(&next).is_none_or(|&known| next_cost < known) {
+                cost.insert(next, next_cost);
+                came_from.insert(next, current);
+                open.push(Reverse((next_cost + heuristic(next, goal), next)));
+            }
+        }
+    }
+
+    None
+}
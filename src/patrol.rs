@@ -0,0 +1,104 @@
+use crate::pathfinding::Cell;
+use crate::rng::Rng;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PatrolMode {
+    /// Walks the waypoints in order and starts over from the first one.
+    Loop,
+    /// Walks the waypoints to the end and back again.
+    PingPong,
+    /// Picks a random waypoint each time one is reached.
+    RandomWalk,
+}
+
+impl PatrolMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatrolMode::Loop => "loop",
+            PatrolMode::PingPong => "pingpong",
+            PatrolMode::RandomWalk => "random",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PatrolMode> {
+        [PatrolMode::Loop, PatrolMode::PingPong, PatrolMode::RandomWalk]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+/// Waypoints an enemy walks between while it isn't chasing anyone. A route
+/// without waypoints keeps the enemy standing guard at its spawn.
+#[derive(Clone, Debug)]
+pub struct PatrolRoute {
+    pub mode: PatrolMode,
+    pub waypoints: Vec<Cell>,
+    current: usize,
+    forward: bool,
+    rng: Rng,
+}
+
+impl PatrolRoute {
+    pub fn new(mode: PatrolMode, waypoints: Vec<Cell>, seed: u64) -> Self {
+        PatrolRoute {
+            mode,
+            waypoints,
+            current: 0,
+            forward: true,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Restarts the random walk from `seed`; other modes ignore it.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Where along the route the enemy is, as `CURRENT FORWARD RNG` words for
+    /// save files.
+    pub fn progress(&self) -> String {
+        format!("{} {} {}", self.current, self.forward, self.rng.state())
+    }
+
+    pub fn restore_progress(&mut self, words: &[&str]) -> Option<()> {
+        let [current, forward, rng] = words else {
+            return None;
+        };
+        self.current = current.parse().ok().filter(|&current| current < self.waypoints.len().max(1))?;
+        self.forward = forward.parse().ok()?;
+        self.rng = Rng::from_state(rng.parse().ok()?);
+        Some(())
+    }
+
+    pub fn idle() -> Self {
+        PatrolRoute::new(PatrolMode::Loop, Vec::new(), 0)
+    }
+
+    /// The waypoint the enemy is currently heading for.
+    pub fn current(&self) -> Option<Cell> {
+        self.waypoints.get(self.current).copied()
+    }
+
+    pub fn advance(&mut self) {
+        let len = self.waypoints.len();
+        if len < 2 {
+            return;
+        }
+
+        self.current = match self.mode {
+            PatrolMode::Loop => (self.current + 1) % len,
+            PatrolMode::PingPong => {
+                if self.forward && self.current + 1 == len {
+                    self.forward = false;
+                } else if !self.forward && self.current == 0 {
+                    self.forward = true;
+                }
+                if self.forward { self.current + 1 } else { self.current - 1 }
+            }
+            PatrolMode::RandomWalk => {
+                let next = self.rng.below(len - 1);
+                if next >= self.current { next + 1 } else { next }
+            }
+        };
+    }
+}
@@ -0,0 +1,61 @@
+use crate::inventory::Item;
+use crate::maze::cell_center;
+use crate::pathfinding::Cell;
+use crate::sprites::Sprite;
+use crate::timestep::ticks;
+
+/// Ticks a speed or invisibility boost lasts.
+pub const BOOST_TICKS: u32 = ticks(8.0);
+/// Walking this close to a pickup collects it.
+pub const PICKUP_RANGE: f32 = 35.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PickupKind {
+    /// An extra life, used straight away.
+    Heart,
+    /// Goes into the inventory.
+    Item(Item),
+}
+
+impl PickupKind {
+    /// Entity name in level files. Colored keys are written `key COLOR`.
+    pub fn name(self) -> String {
+        match self {
+            PickupKind::Heart => "heart".to_string(),
+            PickupKind::Item(item) => item.name(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PickupKind> {
+        match name {
+            "heart" => Some(PickupKind::Heart),
+            _ => Item::from_name(name).map(PickupKind::Item),
+        }
+    }
+
+    pub fn texture_key(self) -> char {
+        match self {
+            PickupKind::Heart => 'h',
+            PickupKind::Item(item) => item.texture_key(),
+        }
+    }
+}
+
+/// An item lying in the maze until the player walks over it.
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub cell: Cell,
+    pub sprite: Sprite,
+}
+
+impl Pickup {
+    pub fn new(kind: PickupKind, cell: Cell, block_size: usize) -> Self {
+        let pos = cell_center(cell, block_size);
+        let mut sprite = Sprite::new(pos.x, pos.y, 0, 0, kind.texture_key(), 64, 64);
+        sprite.scale = 0.4;
+        if let PickupKind::Item(item) = kind {
+            sprite.tint = item.tint();
+        }
+        Pickup { kind, cell, sprite }
+    }
+}
@@ -1,47 +1,146 @@
 use raylib::prelude::*;
 use std::f32::consts::PI;
 
-use crate::maze::{get_cell, Maze};
+use crate::combat::Sword;
+use crate::input::{Action, InputState};
+use crate::inventory::Inventory;
+use crate::maze::{get_cell, is_walkable, slide, Maze};
+use crate::timestep::{per_tick, ticks};
+
+pub const MAX_HEALTH: u32 = 3;
+/// Ticks the player can't be hurt again after taking a hit.
+const INVULNERABLE_TICKS: u32 = ticks(1.5);
+/// Ticks the screen flashes red after a hit.
+const FLASH_TICKS: u32 = ticks(1.0 / 3.0);
+const KNOCKBACK: f32 = per_tick(840.0);
+/// Fraction of knockback speed kept from one tick to the next.
+const KNOCKBACK_DAMPING: f32 = 0.84;
+/// Knockback slower than this has worn off.
+const KNOCKBACK_REST: f32 = per_tick(30.0);
+const RADIUS: f32 = 10.0;
 
 pub struct Player {
     pub pos: Vector2,
     pub a: f32,
     pub fov: f32,
-    pub lives: u32
+    pub lives: u32,
+    /// Radius in world units within which enemies can hear the player this frame.
+    pub noise: f32,
+    pub sword: Sword,
+    pub health: u32,
+    pub invulnerable: u32,
+    pub flash: u32,
+    pub knockback: Vector2,
+    /// Where the player comes back after losing a life.
+    pub checkpoint: Vector2,
+    /// Position and angle before the last tick, for drawing between ticks.
+    pub previous: (Vector2, f32),
+    pub inventory: Inventory,
+    /// Ticks left of the speed boost.
+    pub speed_boost: u32,
+    /// Ticks left of invisibility.
+    pub invisible: u32,
 }
 
-pub fn process_events(rl: &RaylibHandle, player: &mut Player, maze: &Maze, block_size: usize) {
-    const MOVE_SPEED: f32 = 5.0;
-    const ROTATION_SPEED: f32 = PI / 25.0;
+impl Player {
+    /// Takes one point of damage from something at `source` unless still
+    /// invulnerable from the last hit. Returns true if the hit landed.
+    pub fn take_hit(&mut self, source: Vector2) -> bool {
+        if self.invulnerable > 0 || self.health == 0 {
+            return false;
+        }
+
+        self.health -= 1;
+        self.invulnerable = INVULNERABLE_TICKS;
+        self.flash = FLASH_TICKS;
+        let away = self.pos - source;
+        if away.length() > 0.0 {
+            self.knockback = away / away.length() * KNOCKBACK;
+        }
+        true
+    }
+
+    /// Puts the player back at the last checkpoint with full health.
+    pub fn respawn(&mut self) {
+        self.pos = self.checkpoint;
+        self.previous = (self.pos, self.a);
+        self.health = MAX_HEALTH;
+        self.invulnerable = INVULNERABLE_TICKS;
+        self.knockback = Vector2::zero();
+    }
+
+    /// Red overlay for a moment after a hit, fading out.
+    pub fn damage_flash(&self) -> Option<Color> {
+        if self.flash == 0 {
+            return None;
+        }
+        let alpha = 120 * self.flash / FLASH_TICKS;
+        Some(Color::new(255, 0, 0, alpha as u8))
+    }
+}
+
+pub fn process_events(input: &InputState, player: &mut Player, maze: &Maze, block_size: usize) {
+    const MOVE_SPEED: f32 = per_tick(300.0);
+    const SPRINT_MULTIPLIER: f32 = 1.8;
+    const BOOST_MULTIPLIER: f32 = 1.5;
+    const ROTATION_SPEED: f32 = per_tick(PI * 2.4);
+    const SPRINT_NOISE: f32 = 300.0;
+    const ATTACK_NOISE: f32 = 400.0;
+
+    let sprinting = input.is_down(Action::Sprint)
+        && (input.is_down(Action::MoveForward) || input.is_down(Action::MoveBackward));
+    let mut move_speed = if sprinting { MOVE_SPEED * SPRINT_MULTIPLIER } else { MOVE_SPEED };
+    if player.speed_boost > 0 {
+        move_speed *= BOOST_MULTIPLIER;
+    }
+
+    player.invulnerable = player.invulnerable.saturating_sub(1);
+    player.flash = player.flash.saturating_sub(1);
+    player.speed_boost = player.speed_boost.saturating_sub(1);
+    player.invisible = player.invisible.saturating_sub(1);
+    if player.knockback.length() > KNOCKBACK_REST {
+        player.pos = slide(maze, player.pos, player.knockback, RADIUS, block_size);
+        player.knockback *= KNOCKBACK_DAMPING;
+    }
+
+    player.noise = 0.0;
+    if sprinting {
+        player.noise = SPRINT_NOISE;
+    }
+    if input.is_pressed(Action::Attack) && player.sword.try_swing() {
+        player.noise = ATTACK_NOISE;
+    }
 
-    if rl.is_key_down(KeyboardKey::KEY_RIGHT) {
-        player.a += ROTATION_SPEED;
+    if input.is_down(Action::TurnRight) {
+        player.a += ROTATION_SPEED * input.value(Action::TurnRight);
     }
-    if rl.is_key_down(KeyboardKey::KEY_LEFT) {
-        player.a -= ROTATION_SPEED;
+    if input.is_down(Action::TurnLeft) {
+        player.a -= ROTATION_SPEED * input.value(Action::TurnLeft);
     }
 
-    if rl.is_key_down(KeyboardKey::KEY_DOWN) {
-        let new_x = player.pos.x - MOVE_SPEED * player.a.cos();
-        let new_y = player.pos.y - MOVE_SPEED * player.a.sin();
+    if input.is_down(Action::MoveBackward) {
+        let speed = move_speed * input.value(Action::MoveBackward);
+        let new_x = player.pos.x - speed * player.a.cos();
+        let new_y = player.pos.y - speed * player.a.sin();
 
         let cell = get_cell(&maze, new_x, new_y, block_size);
         if let Some(c) = cell {
-            if c == ' ' {
-                player.pos.x -= MOVE_SPEED * player.a.cos();
-                player.pos.y -= MOVE_SPEED * player.a.sin();
+            if is_walkable(c) {
+                player.pos.x -= speed * player.a.cos();
+                player.pos.y -= speed * player.a.sin();
             } 
         }
     }
-    if rl.is_key_down(KeyboardKey::KEY_UP) {
-        let new_x = player.pos.x + MOVE_SPEED * player.a.cos();
-        let new_y = player.pos.y + MOVE_SPEED * player.a.sin();
+    if input.is_down(Action::MoveForward) {
+        let speed = move_speed * input.value(Action::MoveForward);
+        let new_x = player.pos.x + speed * player.a.cos();
+        let new_y = player.pos.y + speed * player.a.sin();
 
         let cell = get_cell(&maze, new_x, new_y, block_size);
         if let Some(c) = cell {
-            if c == ' ' {
-                player.pos.x += MOVE_SPEED * player.a.cos();
-                player.pos.y += MOVE_SPEED * player.a.sin();
+            if is_walkable(c) {
+                player.pos.x += speed * player.a.cos();
+                player.pos.y += speed * player.a.sin();
             } 
         }
     }
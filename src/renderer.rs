@@ -1,9 +1,20 @@
 use crate::framebuffer::Framebuffer;
-use crate::maze::Maze;
-use crate::player::Player;
+use crate::hazard::{spikes_raised, Hazard, SHIFT_WALL};
+use crate::input::{Action, InputBindings, Rebinder};
+use crate::inventory::{Item, KeyColor};
+use crate::maze::{cell_center, get_cell, is_walkable, Maze};
+use crate::minimap::{Automap, MinimapConfig};
+use crate::patrol::{PatrolMode, PatrolRoute};
+use crate::pickup::Pickup;
+use crate::settings::{Settings, SettingsEntry, SettingsMenu};
+use crate::player::{Player, MAX_HEALTH};
+use crate::score::format_time;
 use crate::caster::cast_ray;
+use crate::enemy::Enemy;
 use crate::sprites::Sprite;
 use crate::textures::TextureManager;
+use crate::timestep::TICK;
+use crate::world::World;
 use raylib::prelude::*;
 use std::f32::consts::PI;
 
@@ -11,6 +22,7 @@ pub fn draw_sprite(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
     player: &Player,
+    block_size: usize,
     sprite: &Sprite,
     texture_manager: &TextureManager,
 ) {
@@ -34,17 +46,21 @@ pub fn draw_sprite(
         return;
     }
 
-    let ray = cast_ray(framebuffer, maze, player, sprite_a, 100, false);
+    let ray = cast_ray(framebuffer, maze, player, sprite_a, block_size, false);
     let behind_wall = sprite_d >= ray.distance;
     if behind_wall {
         return;
     }
 
-    let sprite_size = (screen_height / sprite_d) * 40.0;
+    let full_size = (screen_height / sprite_d) * 40.0;
+    let sprite_size = full_size * sprite.scale;
+    if sprite_size < 1.0 {
+        return;
+    }
     let screen_x = ((angle_difference / player.fov) + 0.5) * screen_width;
 
     let start_x = (screen_x - sprite_size / 2.0).max(0.0) as usize;
-    let start_y = (screen_height / 2.0 - sprite_size / 2.0).max(0.0) as usize;
+    let start_y = (screen_height / 2.0 + full_size / 2.0 - sprite_size).max(0.0) as usize;
 
     let end_x = (start_x + sprite_size as usize).min(framebuffer.width as usize);
     let end_y = (start_y + sprite_size as usize).min(framebuffer.height as usize);
@@ -56,65 +72,187 @@ pub fn draw_sprite(
 
             let color = texture_manager.get_pixel_color(sprite.texture_key, tx, ty);
             if color.a > 0 {
-                framebuffer.set_current_color(color);
+                framebuffer.set_current_color(tint(color, sprite.tint));
                 framebuffer.set_pixel(x as u32, y as u32);
             }
         }
     }
 }
 
-fn draw_cell(framebuffer: &mut Framebuffer, x0: usize, y0: usize, block_size: usize, cell: char) {
-    if cell == ' ' {
-        return;
+fn tint(color: Color, tint: Color) -> Color {
+    let channel = |a: u8, b: u8| (a as u16 * b as u16 / 255) as u8;
+    Color::new(channel(color.r, tint.r), channel(color.g, tint.g), channel(color.b, tint.b), color.a)
+}
+
+fn cell_to_texture_color(texture_cache: &TextureManager, cell: char, tx: u32, ty: u32) -> Color {
+    texture_cache.get_pixel_color(cell, tx, ty)
+}
+
+/// Map color of a floor cell, with hazards picked out.
+fn floor_color(cell: char) -> Color {
+    match Hazard::from_cell(cell) {
+        Some(Hazard::Spikes) => Color::new(150, 150, 165, 255),
+        Some(Hazard::Plate) => Color::BEIGE,
+        Some(Hazard::Poison) => Color::DARKGREEN,
+        None => Color::DARKGRAY,
     }
-    framebuffer.set_current_color(Color::VIOLET);
+}
 
-    for x in x0..x0 + block_size {
-        for y in y0..y0 + block_size {
-            framebuffer.set_pixel(x as u32, y as u32);
+fn fill_rect(framebuffer: &mut Framebuffer, x0: f32, y0: f32, width: f32, height: f32, color: Color) {
+    framebuffer.set_current_color(color);
+    for x in x0.max(0.0) as u32..(x0 + width).max(0.0) as u32 {
+        for y in y0.max(0.0) as u32..(y0 + height).max(0.0) as u32 {
+            framebuffer.set_pixel(x, y);
         }
     }
 }
 
-fn cell_to_texture_color(texture_cache: &TextureManager, cell: char, tx: u32, ty: u32) -> Color {
-    texture_cache.get_pixel_color(cell, tx, ty)
+fn draw_marker(framebuffer: &mut Framebuffer, center: Vector2, radius: f32, color: Color) {
+    framebuffer.set_current_color(color);
+    let r = radius as i32;
+    for dx in -r..=r {
+        for dy in -r..=r {
+            if dx * dx + dy * dy <= r * r {
+                let x = center.x as i32 + dx;
+                let y = center.y as i32 + dy;
+                if x >= 0 && y >= 0 {
+                    framebuffer.set_pixel(x as u32, y as u32);
+                }
+            }
+        }
+    }
 }
 
-pub fn render_maze(
+fn draw_line(framebuffer: &mut Framebuffer, from: Vector2, to: Vector2, color: Color) {
+    framebuffer.set_current_color(color);
+    let steps = from.distance_to(to).ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let point = from.lerp(to, step as f32 / steps as f32);
+        if point.x >= 0.0 && point.y >= 0.0 {
+            framebuffer.set_pixel(point.x as u32, point.y as u32);
+        }
+    }
+}
+
+/// Full-screen top-down view. The maze is scaled to fit the window and
+/// centered, with the player's field of view drawn as a cone of rays.
+#[allow(clippy::too_many_arguments)]
+pub fn render_map(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
+    automap: &Automap,
+    config: &MinimapConfig,
     block_size: usize,
     player: &Player,
+    enemies: &[Enemy],
+    princess: &Sprite,
+    checkpoints: &[Vector2],
 ) {
+    let rows = maze.len();
+    let cols = maze.iter().map(|row| row.len()).max().unwrap_or(0);
+    if rows == 0 || cols == 0 {
+        return;
+    }
+
+    let margin = 20.0;
+    let world_width = (cols * block_size) as f32;
+    let world_height = (rows * block_size) as f32;
+    let scale = ((framebuffer.width as f32 - 2.0 * margin) / world_width)
+        .min((framebuffer.height as f32 - 2.0 * margin) / world_height);
+    let offset = Vector2::new(
+        (framebuffer.width as f32 - world_width * scale) / 2.0,
+        (framebuffer.height as f32 - world_height * scale) / 2.0,
+    );
+    let to_screen = |pos: Vector2| offset + pos * scale;
+
+    fill_rect(framebuffer, 0.0, 0.0, framebuffer.width as f32, framebuffer.height as f32, Color::BLACK);
+    let cell_size = block_size as f32 * scale;
     for (row_index, row) in maze.iter().enumerate() {
         for (col_index, &cell) in row.iter().enumerate() {
-            let x0 = col_index * block_size;
-            let y0 = row_index * block_size;
-            draw_cell(framebuffer, x0, y0, block_size, cell);
+            if !config.debug && !automap.is_seen(col_index, row_index) {
+                continue;
+            }
+            let color = match (cell, KeyColor::from_door(cell)) {
+                (cell, _) if is_walkable(cell) => floor_color(cell),
+                (SHIFT_WALL, _) => Color::DARKPURPLE,
+                (_, Some(KeyColor::Iron)) => Color::BROWN,
+                (_, Some(color)) => color.tint(),
+                _ => Color::VIOLET,
+            };
+            let x0 = offset.x + col_index as f32 * cell_size;
+            let y0 = offset.y + row_index as f32 * cell_size;
+            fill_rect(framebuffer, x0, y0, cell_size.ceil(), cell_size.ceil(), color);
         }
     }
 
-    framebuffer.set_current_color(Color::RED);
-    let px = player.pos.x as u32;
-    let py = player.pos.y as u32;
-    framebuffer.set_pixel(px, py);
-
-    let num_rays = 5;
-    for i in 0..num_rays {
+    let origin = to_screen(player.pos);
+    let num_rays = 90;
+    for i in 0..=num_rays {
         let current_ray = i as f32 / num_rays as f32;
         let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        cast_ray(framebuffer, maze, player, a, block_size, true);
+        let intersect = cast_ray(framebuffer, maze, player, a, block_size, false);
+        let hit = Vector2::new(
+            player.pos.x + intersect.distance * a.cos(),
+            player.pos.y + intersect.distance * a.sin(),
+        );
+        draw_line(framebuffer, origin, to_screen(hit), Color::GOLD);
+    }
+
+    let marker = (cell_size / 6.0).max(3.0);
+    if config.debug {
+        for enemy in enemies {
+            render_route(framebuffer, &enemy.route, block_size, &to_screen, marker);
+        }
+    }
+
+    for &checkpoint in checkpoints.iter().filter(|&&checkpoint| config.debug || automap.is_seen_at(checkpoint, block_size)) {
+        let color = if checkpoint == player.checkpoint { Color::LIME } else { Color::DARKGREEN };
+        draw_marker(framebuffer, to_screen(checkpoint), marker, color);
+    }
+    if config.debug || automap.is_seen_at(princess.pos, block_size) {
+        draw_marker(framebuffer, to_screen(princess.pos), marker, Color::PINK);
+    }
+    for enemy in enemies.iter().filter(|enemy| config.debug || automap.is_seen_at(enemy.pos(), block_size)) {
+        let pos = to_screen(enemy.pos());
+        draw_marker(framebuffer, pos, marker, Color::RED);
+        if config.debug {
+            let heading = Vector2::new(enemy.heading.cos(), enemy.heading.sin()) * marker * 3.0;
+            draw_line(framebuffer, pos, pos + heading, Color::RED);
+        }
     }
+    draw_marker(framebuffer, origin, marker, Color::WHITE);
 }
 
+/// Draws a patrol route on the debug map, closing the loop for looping
+/// routes and highlighting the waypoint the enemy is heading for.
+fn render_route(framebuffer: &mut Framebuffer, route: &PatrolRoute, block_size: usize, to_screen: &dyn Fn(Vector2) -> Vector2, marker: f32) {
+    let points: Vec<Vector2> = route.waypoints.iter().map(|&cell| to_screen(cell_center(cell, block_size))).collect();
+
+    for pair in points.windows(2) {
+        draw_line(framebuffer, pair[0], pair[1], Color::ORANGE);
+    }
+    if route.mode == PatrolMode::Loop && points.len() > 2 {
+        draw_line(framebuffer, points[points.len() - 1], points[0], Color::ORANGE);
+    }
+
+    for (&point, cell) in points.iter().zip(&route.waypoints) {
+        let color = if route.current() == Some(*cell) { Color::YELLOW } else { Color::ORANGE };
+        draw_marker(framebuffer, point, marker / 2.0, color);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn render_3d(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
     player: &Player,
     block_size: usize,
     texture_manager: &TextureManager,
+    render_scale: f32,
+    spikes_raised: bool,
 ) {
-    let num_rays = framebuffer.width;
+    // Lower render scales cast fewer rays and stretch each one over several columns.
+    let num_rays = ((framebuffer.width as f32 * render_scale) as u32).clamp(1, framebuffer.width);
     let hh = framebuffer.height as f32 / 2.0;
 
     for y in 0..hh as usize {
@@ -137,6 +275,9 @@ pub fn render_3d(
         let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
         let intersect = cast_ray(framebuffer, &maze, &player, a, block_size, false);
 
+        let column_start = i * framebuffer.width / num_rays;
+        let column_end = (i + 1) * framebuffer.width / num_rays;
+
         let distance_to_wall = intersect.distance;
         let distance_to_projection_plane = 120.0;
         let stake_height = (hh / distance_to_wall) * distance_to_projection_plane;
@@ -149,102 +290,187 @@ pub fn render_3d(
 
             let color = cell_to_texture_color(texture_manager, intersect.impact, intersect.tx as u32, ty as u32);
             framebuffer.set_current_color(color);
-            framebuffer.set_pixel(i, y as u32);
+            for x in column_start..column_end {
+                framebuffer.set_pixel(x, y as u32);
+            }
+        }
+
+        // Hazard cells get a textured floor; the rest of the floor stays flat.
+        // Each floor row is as far away as a wall whose bottom edge it would be.
+        for y in stake_bottom.max(hh as usize + 1)..framebuffer.height as usize {
+            let distance = hh * distance_to_projection_plane / 2.0 / (y as f32 - hh);
+            let floor = player.pos + Vector2::new(a.cos(), a.sin()) * distance;
+            let Some(hazard) = get_cell(maze, floor.x, floor.y, block_size).and_then(Hazard::from_cell) else {
+                continue;
+            };
+            let tx = (floor.x as usize % block_size * 64 / block_size) as u32;
+            let ty = (floor.y as usize % block_size * 64 / block_size) as u32;
+            framebuffer.set_current_color(texture_manager.get_pixel_color(hazard.texture_key(spikes_raised), tx, ty));
+            for x in column_start..column_end {
+                framebuffer.set_pixel(x, y as u32);
+            }
         }
     }
 }
 
-pub fn render_sword(framebuffer: &mut Framebuffer, texture_cache: &TextureManager) {
+/// Draws the sword overlay. While swinging it is lifted to the right and
+/// rotated clockwise around its hilt, following `swing` from 0 to 1.
+pub fn render_sword(framebuffer: &mut Framebuffer, texture_cache: &TextureManager, swing: Option<f32>) {
     let sword_width = 64;
     let sword_heigth = 64;
     let scale = 5.0;
-    let display_width = (sword_width as f32 * scale) as u32;
-    let display_height = (sword_heigth as f32 * scale) as u32;
+    let display_width = sword_width as f32 * scale;
+    let display_height = sword_heigth as f32 * scale;
     let ui_x = 10.0;
-    let ui_y = (framebuffer.height - display_height) as usize;
 
-    for ty_disp in 0..display_height {
-        for tx_disp in 0..display_width {
-            let tx = (tx_disp as f32 / scale) as u32;
-            let ty = (ty_disp as f32 / scale) as u32;
+    let lift = swing.map_or(0.0, |progress| (progress * PI).sin());
+    let angle = lift * 1.2;
+    let (sin, cos) = angle.sin_cos();
+    let hilt = Vector2::new(ui_x + lift * display_width * 0.8, framebuffer.height as f32);
+
+    let reach = (display_width * display_width + display_height * display_height).sqrt();
+    let min_x = (hilt.x - reach).max(0.0) as u32;
+    let max_x = (hilt.x + reach).min(framebuffer.width as f32) as u32;
+    let min_y = (hilt.y - reach).max(0.0) as u32;
+
+    for y in min_y..framebuffer.height {
+        for x in min_x..max_x {
+            // Undo the rotation to find this pixel on the upright sword.
+            let dx = x as f32 - hilt.x;
+            let dy = y as f32 - hilt.y;
+            let local_x = dx * cos + dy * sin;
+            let local_y = -dx * sin + dy * cos + display_height;
+            if local_x < 0.0 || local_x >= display_width || local_y < 0.0 || local_y >= display_height {
+                continue;
+            }
+
+            let tx = (local_x / scale) as u32;
+            let ty = (local_y / scale) as u32;
             let color = texture_cache.get_pixel_color('s', tx, ty);
             if color.a == 0 {
                 continue;
             }
-            let x = ui_x as u32 + tx_disp;
-            let y = ui_y as u32 + ty_disp;
             framebuffer.set_current_color(color);
             framebuffer.set_pixel(x, y);
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn render_minmap(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
-    block_size: usize,
+    automap: &Automap,
+    config: &MinimapConfig,
     world_block_size: usize,
     player: &Player,
     princess: &Sprite,
-    enemies: &mut [Sprite],
-    pos: Vector2
+    enemies: &[Enemy],
 ) {
-    for (row_index, row) in maze.iter().enumerate() {
-        for (col_index, &cell) in row.iter().enumerate() {
-            let x0 = pos.x as usize + col_index * block_size;
-            let y0 = pos.y as usize + row_index * block_size;
-            draw_cell(framebuffer, x0, y0, block_size, cell);
-        }
-    }
-
-    framebuffer.set_current_color(Color::BLACK);
-    let scale = block_size as f32 / world_block_size as f32;
-
-    let px = pos.x as f32 + player.pos.x * scale;
-    let py = pos.y as f32 + player.pos.y * scale;
+    let center = config.center();
+    let scale = config.cell_size() / world_block_size as f32;
+    let rotation = config.rotation(player.a);
+    let half = config.size as i32 / 2;
+
+    for dy in -half..half {
+        for dx in -half..half {
+            let offset = Vector2::new(dx as f32, dy as f32);
+            if !config.contains(offset) {
+                continue;
+            }
 
-    let square_size = 5;
-    for i in 1..square_size {
-        for j in 1..square_size {
-            framebuffer.set_pixel(px as u32 + i, py as u32 + j);
+            let world = player.pos + offset.rotated(-rotation) / scale;
+            let color = if world.x < 0.0 || world.y < 0.0 || !automap.is_seen_at(world, world_block_size) {
+                Color::BLACK
+            } else {
+                match get_cell(maze, world.x, world.y, world_block_size) {
+                    Some(cell) if is_walkable(cell) => floor_color(cell),
+                    Some(_) => Color::VIOLET,
+                    None => Color::BLACK,
+                }
+            };
+
+            let x = center.x as i32 + dx;
+            let y = center.y as i32 + dy;
+            if x >= 0 && y >= 0 {
+                framebuffer.set_current_color(color);
+                framebuffer.set_pixel(x as u32, y as u32);
+            }
         }
     }
 
-    let bx = pos.x as f32 + princess.pos.x * scale;
-    let by = pos.y as f32 + princess.pos.y * scale;
-    framebuffer.set_current_color(Color::PINK);
-    for i in 1..square_size {
-        for j in 1..square_size {
-            framebuffer.set_pixel(bx as u32 + i, by as u32 + j);
+    let to_minimap = |pos: Vector2| (pos - player.pos).rotated(rotation) * scale;
+    let marker = (config.cell_size() / 5.0).max(2.0);
+
+    if automap.is_seen_at(princess.pos, world_block_size) {
+        let offset = to_minimap(princess.pos);
+        if config.contains(offset) {
+            draw_marker(framebuffer, center + offset, marker, Color::PINK);
         }
     }
 
-    framebuffer.set_current_color(Color::RED);
-    for enemy in enemies {
-        let ex = pos.x as f32 + enemy.pos.x * scale;
-        let ey = pos.y as f32 + enemy.pos.y * scale;
-
-        for i in 1..square_size {
-            for j in 1..square_size {
-                framebuffer.set_pixel(ex as u32 + i, ey as u32 + j);
+    if config.show_enemies {
+        for enemy in enemies.iter().filter(|enemy| automap.is_seen_at(enemy.pos(), world_block_size)) {
+            let offset = to_minimap(enemy.pos());
+            if config.contains(offset) {
+                draw_marker(framebuffer, center + offset, marker, Color::RED);
             }
         }
     }
-}
 
+    let heading = Vector2::new(player.a.cos(), player.a.sin()).rotated(rotation) * (marker * 3.0);
+    draw_line(framebuffer, center, center + heading, Color::WHITE);
+    draw_marker(framebuffer, center, marker, Color::WHITE);
+}
 
 pub fn render_enemies(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
     player: &Player,
+    block_size: usize,
     texture_manager: &TextureManager,
-    enemies: &mut [Sprite],
+    enemies: &[Enemy],
     princess: &Sprite
 ) {
-    for enemy in enemies.iter_mut() {
-        draw_sprite(framebuffer, maze, player, enemy, texture_manager);
+    for enemy in enemies {
+        draw_sprite(framebuffer, maze, player, block_size, &enemy.sprite, texture_manager);
+    }
+    draw_sprite(framebuffer, maze, player, block_size, princess, texture_manager);
+}
+
+/// Pickups still lying around, farthest first so nearer ones cover them.
+pub fn render_pickups(
+    framebuffer: &mut Framebuffer,
+    maze: &Maze,
+    player: &Player,
+    block_size: usize,
+    texture_manager: &TextureManager,
+    pickups: &[Pickup],
+) {
+    let mut sprites: Vec<&Sprite> = pickups.iter().map(|pickup| &pickup.sprite).collect();
+    sprites.sort_by(|a, b| player.pos.distance_to(b.pos).total_cmp(&player.pos.distance_to(a.pos)));
+    for sprite in sprites {
+        draw_sprite(framebuffer, maze, player, block_size, sprite, texture_manager);
+    }
+}
+
+/// The in-game first-person frame: walls, minimap, sword, sprites and HUD.
+pub fn render_first_person(
+    framebuffer: &mut Framebuffer,
+    world: &World,
+    texture_manager: &TextureManager,
+    minimap: Option<&MinimapConfig>,
+    render_scale: f32,
+) {
+    let spikes = spikes_raised(world.tick);
+    render_3d(framebuffer, &world.maze, &world.player, world.block_size, texture_manager, render_scale, spikes);
+    if let Some(minimap) = minimap {
+        render_minmap(framebuffer, &world.maze, &world.automap, minimap, world.block_size, &world.player, &world.princess, &world.enemies);
     }
-    draw_sprite(framebuffer, &maze, player, princess, texture_manager);
+    render_sword(framebuffer, texture_manager, world.player.sword.progress());
+    render_pickups(framebuffer, &world.maze, &world.player, world.block_size, texture_manager, &world.pickups);
+    render_enemies(framebuffer, &world.maze, &world.player, world.block_size, texture_manager, &world.enemies, &world.princess);
+    render_lives(framebuffer, texture_manager, &world.player);
 }
 
 pub fn render_live(framebuffer: &mut Framebuffer, texture_cache: &TextureManager, start_x: u32, start_y: u32) {
@@ -283,9 +509,15 @@ pub fn render_lives(
         let start_x = 10 + i * (icon_width + spacing);
         render_live(framebuffer, texture_cache, start_x as u32, start_y);
     }
+
+    let bar_width = 240.0;
+    let bar_y = start_y as f32 + 90.0;
+    let filled = bar_width * player.health as f32 / MAX_HEALTH as f32;
+    fill_rect(framebuffer, 10.0, bar_y, bar_width, 12.0, Color::DARKGRAY);
+    fill_rect(framebuffer, 10.0, bar_y, filled, 12.0, Color::RED);
 }
 
-pub fn render_start(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache: &TextureManager) {
+pub fn render_start(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache: &TextureManager, entries: &[&str], selected: usize, prompt: &str) {
     d.clear_background(Color::BLACK);
 
     let logo = texture_cache.get_texture('t').unwrap();
@@ -306,9 +538,91 @@ pub fn render_start(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache:
     let inst_x = (screen_width as i32 / 2) - (instructions_width / 2);
     let inst_y = logo_y + logo_height + 60;
     d.draw_text(instructions, inst_x, inst_y, font_size, Color::WHITE);
+
+    render_menu(d, screen_width, entries, selected, inst_y + 80);
+    render_prompt(d, screen_width, inst_y + 100 + entries.len() as i32 * 45, prompt);
+}
+
+pub fn render_prompt(d: &mut RaylibDrawHandle, screen_width: u32, y: i32, prompt: &str) {
+    let font_size = 20;
+    let prompt_width = d.measure_text(prompt, font_size);
+    d.draw_text(prompt, (screen_width as i32 / 2) - (prompt_width / 2), y, font_size, Color::GRAY);
 }
 
-pub fn render_game_over(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache: &TextureManager) {
+pub fn render_menu(d: &mut RaylibDrawHandle, screen_width: u32, entries: &[&str], selected: usize, start_y: i32) {
+    let font_size = 30;
+    let spacing = 45;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let text = if i == selected { format!("> {} <", entry) } else { entry.to_string() };
+        let color = if i == selected { Color::GOLD } else { Color::WHITE };
+        let text_width = d.measure_text(&text, font_size);
+        let x = (screen_width as i32 / 2) - (text_width / 2);
+        d.draw_text(&text, x, start_y + i as i32 * spacing, font_size, color);
+    }
+}
+
+pub fn render_controls(d: &mut RaylibDrawHandle, screen_width: u32, screen_height: u32, bindings: &InputBindings, rebinder: &Rebinder, gamepad: bool) {
+    d.clear_background(Color::BLACK);
+
+    let title = "Controls";
+    let title_width = d.measure_text(title, 50);
+    d.draw_text(title, (screen_width as i32 / 2) - (title_width / 2), 60, 50, Color::WHITE);
+
+    let start_y = 160;
+    // Shrink rows so every action plus the help line fits on screen.
+    let spacing = ((screen_height as i32 - start_y - 60) / Action::ALL.len() as i32).clamp(20, 45);
+    let font_size = (spacing * 28 / 45).max(16);
+    let label_x = screen_width as i32 / 2 - 350;
+    let keys_x = screen_width as i32 / 2 + 50;
+
+    for (i, action) in Action::ALL.iter().enumerate() {
+        let y = start_y + i as i32 * spacing;
+        let selected = i == rebinder.selected;
+        let color = if selected { Color::GOLD } else { Color::WHITE };
+        let keys = if selected && rebinder.waiting { "press a key or button...".to_string() } else { bindings.describe(*action) };
+
+        d.draw_text(action.label(), label_x, y, font_size, color);
+        d.draw_text(&keys, keys_x, y, font_size, color);
+    }
+
+    let help = format!(
+        "{}: add binding   DELETE: clear   {}: save and return",
+        bindings.prompt(Action::Confirm, gamepad),
+        bindings.prompt(Action::Back, gamepad)
+    );
+    let help_y = start_y + Action::ALL.len() as i32 * spacing + 15;
+    render_prompt(d, screen_width, help_y, &help);
+}
+
+pub fn render_settings(d: &mut RaylibDrawHandle, screen_width: u32, settings: &Settings, menu: &SettingsMenu, prompt: &str) {
+    d.clear_background(Color::BLACK);
+
+    let title = "Settings";
+    let title_width = d.measure_text(title, 50);
+    d.draw_text(title, (screen_width as i32 / 2) - (title_width / 2), 60, 50, Color::WHITE);
+
+    let font_size = 30;
+    let spacing = 45;
+    let label_x = screen_width as i32 / 2 - 300;
+    let value_x = screen_width as i32 / 2 + 100;
+    let start_y = 150;
+
+    for (i, entry) in SettingsEntry::ALL.iter().enumerate() {
+        let y = start_y + i as i32 * spacing;
+        let selected = i == menu.selected;
+        let color = if selected { Color::GOLD } else { Color::WHITE };
+        let value = entry.value(settings);
+        let value = if selected && !value.is_empty() { format!("< {} >", value) } else { value };
+
+        d.draw_text(entry.label(), label_x, y, font_size, color);
+        d.draw_text(&value, value_x, y, font_size, color);
+    }
+
+    render_prompt(d, screen_width, start_y + SettingsEntry::ALL.len() as i32 * spacing + 30, prompt);
+}
+
+pub fn render_game_over(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache: &TextureManager, entries: &[&str], selected: usize, prompt: &str) {
     d.clear_background(Color::BLACK);
 
     let logo = texture_cache.get_texture('o').unwrap();
@@ -320,10 +634,27 @@ pub fn render_game_over(d: &mut RaylibDrawHandle, screen_width: u32, texture_cac
     let logo_y = 180;
 
     d.draw_texture_ex(&logo, Vector2::new(logo_x as f32, logo_y as f32), 0.0, scale, Color::WHITE);
+
+    let logo_height = (logo.height as f32 * scale) as i32;
+    let menu_y = logo_y + logo_height + 60;
+    render_menu(d, screen_width, entries, selected, menu_y);
+    render_prompt(d, screen_width, menu_y + 20 + entries.len() as i32 * 45, prompt);
 }
 
 
-pub fn render_victory(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache: &TextureManager) {
+/// `lines` are the title and stats of the finished level, shown under the
+/// logo; `high_scores` is the level's table, shown beside it.
+#[allow(clippy::too_many_arguments)]
+pub fn render_victory(
+    d: &mut RaylibDrawHandle,
+    screen_width: u32,
+    texture_cache: &TextureManager,
+    lines: &[String],
+    high_scores: &[String],
+    entries: &[&str],
+    selected: usize,
+    prompt: &str,
+) {
     d.clear_background(Color::BLACK);
 
     let logo = texture_cache.get_texture('w').unwrap();
@@ -332,7 +663,139 @@ pub fn render_victory(d: &mut RaylibDrawHandle, screen_width: u32, texture_cache
     let logo_width = (logo.width as f32 * scale) as i32;
 
     let logo_x = (screen_width as i32 / 2) - (logo_width / 2);
-    let logo_y = 180;
+    let logo_y = 120;
 
     d.draw_texture_ex(&logo, Vector2::new(logo_x as f32, logo_y as f32), 0.0, scale, Color::WHITE);
-}
\ No newline at end of file
+
+    let logo_height = (logo.height as f32 * scale) as i32;
+    let mut y = logo_y + logo_height + 30;
+    for (i, line) in lines.iter().enumerate() {
+        let (font_size, color) = if i == 0 { (36, Color::GOLD) } else { (26, Color::WHITE) };
+        let line_width = d.measure_text(line, font_size);
+        d.draw_text(line, (screen_width as i32 / 2) - (line_width / 2), y, font_size, color);
+        y += font_size + 12;
+    }
+
+    let table_x = screen_width as i32 - 380;
+    for (i, line) in high_scores.iter().enumerate() {
+        let (font_size, color) = if i == 0 { (30, Color::GOLD) } else { (24, Color::WHITE) };
+        d.draw_text(line, table_x, logo_y + i as i32 * 36, font_size, color);
+    }
+
+    let menu_y = y + 30;
+    render_menu(d, screen_width, entries, selected, menu_y);
+    render_prompt(d, screen_width, menu_y + 20 + entries.len() as i32 * 45, prompt);
+}
+
+/// Drawn over the dimmed last gameplay frame.
+pub fn render_pause(d: &mut RaylibDrawHandle, screen_width: u32, entries: &[&str], selected: usize, prompt: &str) {
+    let title = "Paused";
+    let title_width = d.measure_text(title, 50);
+    d.draw_text(title, (screen_width as i32 / 2) - (title_width / 2), 200, 50, Color::WHITE);
+
+    render_menu(d, screen_width, entries, selected, 300);
+    render_prompt(d, screen_width, 320 + entries.len() as i32 * 45, prompt);
+}
+
+/// Save or load slots with a summary of what each holds, or campaign levels
+/// marked when locked. Doesn't clear the screen, so the save screen can sit
+/// over the paused game.
+pub fn render_slots(d: &mut RaylibDrawHandle, screen_width: u32, title: &str, entries: &[&str], descriptions: &[String], selected: usize, prompt: &str) {
+    let title_width = d.measure_text(title, 50);
+    d.draw_text(title, (screen_width as i32 / 2) - (title_width / 2), 150, 50, Color::WHITE);
+
+    let font_size = 30;
+    let spacing = 50;
+    let label_x = screen_width as i32 / 2 - 300;
+    let value_x = screen_width as i32 / 2 - 20;
+    let start_y = 260;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let y = start_y + i as i32 * spacing;
+        let color = if i == selected { Color::GOLD } else { Color::WHITE };
+        d.draw_text(entry, label_x, y, font_size, color);
+        if let Some(description) = descriptions.get(i) {
+            d.draw_text(description, value_x, y, font_size, if i == selected { Color::GOLD } else { Color::GRAY });
+        }
+    }
+
+    render_prompt(d, screen_width, start_y + entries.len() as i32 * spacing + 30, prompt);
+}
+
+pub fn render_name_entry(d: &mut RaylibDrawHandle, screen_width: u32, score: u32, name: &str, prompt: &str) {
+    d.clear_background(Color::BLACK);
+
+    let title = "New high score!";
+    let title_width = d.measure_text(title, 50);
+    d.draw_text(title, (screen_width as i32 / 2) - (title_width / 2), 200, 50, Color::GOLD);
+
+    let score = format!("Score  {}", score);
+    let score_width = d.measure_text(&score, 30);
+    d.draw_text(&score, (screen_width as i32 / 2) - (score_width / 2), 290, 30, Color::WHITE);
+
+    let name = format!("Name: {}_", name);
+    let name_width = d.measure_text(&name, 40);
+    d.draw_text(&name, (screen_width as i32 / 2) - (name_width / 2), 380, 40, Color::WHITE);
+
+    render_prompt(d, screen_width, 460, prompt);
+}
+
+/// Run timer, score, inventory and boosts under the lives and health bar.
+/// Potions show the key that drinks them.
+pub fn render_hud(d: &mut RaylibDrawHandle, texture_cache: &TextureManager, elapsed: f32, score: u32, player: &Player, potion_keys: [&str; 2]) {
+    d.draw_text(&format_time(elapsed), 10, 122, 28, Color::WHITE);
+    d.draw_text(&format!("Score {}", score), 110, 122, 28, Color::GOLD);
+
+    let mut x = 10;
+    for &(item, count) in player.inventory.items() {
+        if let Some(icon) = texture_cache.get_texture(item.texture_key()) {
+            d.draw_texture_ex(icon, Vector2::new(x as f32, 154.0), 0.0, 0.5, item.tint());
+        }
+        let label = match item {
+            Item::SpeedPotion => format!("{} [{}]", count, potion_keys[0]),
+            Item::InvisibilityPotion => format!("{} [{}]", count, potion_keys[1]),
+            _ => count.to_string(),
+        };
+        d.draw_text(&label, x + 34, 160, 22, Color::WHITE);
+        x += 44 + d.measure_text(&label, 22);
+    }
+
+    let seconds = |ticks: u32| (ticks as f32 * TICK).ceil();
+    let mut boosts = Vec::new();
+    if player.speed_boost > 0 {
+        boosts.push(format!("Speed {}s", seconds(player.speed_boost)));
+    }
+    if player.invisible > 0 {
+        boosts.push(format!("Invisible {}s", seconds(player.invisible)));
+    }
+    d.draw_text(&boosts.join("   "), 10, 196, 22, Color::WHITE);
+}
+
+/// Short message near the bottom of the screen, e.g. after a quick save.
+pub fn render_notice(d: &mut RaylibDrawHandle, screen_width: u32, screen_height: u32, text: &str) {
+    let font_size = 28;
+    let text_width = d.measure_text(text, font_size);
+    d.draw_text(text, (screen_width as i32 / 2) - (text_width / 2), screen_height as i32 - 80, font_size, Color::GOLD);
+}
+
+pub fn render_credits(d: &mut RaylibDrawHandle, screen_width: u32, prompt: &str) {
+    d.clear_background(Color::BLACK);
+
+    let title = "Credits";
+    let title_width = d.measure_text(title, 50);
+    d.draw_text(title, (screen_width as i32 / 2) - (title_width / 2), 150, 50, Color::WHITE);
+
+    let lines = [
+        "Knight Maze",
+        "A raycasting adventure built with Rust and raylib",
+        "Audio playback with rodio",
+        "Thanks for playing!",
+    ];
+    let font_size = 28;
+    for (i, line) in lines.iter().enumerate() {
+        let line_width = d.measure_text(line, font_size);
+        d.draw_text(line, (screen_width as i32 / 2) - (line_width / 2), 260 + i as i32 * 50, font_size, Color::WHITE);
+    }
+
+    render_prompt(d, screen_width, 260 + lines.len() as i32 * 50 + 40, prompt);
+}
@@ -0,0 +1,216 @@
+use std::fs;
+use std::io;
+
+use crate::input::InputState;
+use crate::level::{Level, LevelSource};
+use crate::timestep::TICK;
+use crate::world::{World, WorldEvent};
+
+const HEADER: &str = "knight-maze replay";
+pub const VERSION: u32 = 1;
+
+/// Everything needed to play a run again exactly: the level, the world seed
+/// and the input of every simulation tick.
+///
+/// The file is plain text: a `knight-maze replay VERSION` header, `key value`
+/// lines, then a `ticks` line followed by `COUNT INPUT` lines, where INPUT is
+/// held for COUNT ticks in a row.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub level: LevelSource,
+    pub block_size: usize,
+    pub seed: u64,
+    pub ticks: Vec<InputState>,
+}
+
+impl Replay {
+    pub fn new(level: LevelSource, block_size: usize, seed: u64) -> Self {
+        Replay { level, block_size, seed, ticks: Vec::new() }
+    }
+
+    pub fn record(&mut self, input: &InputState) {
+        self.ticks.push(input.clone());
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{} {}\nlevel {}\nblock_size {}\nseed {}\nticks\n",
+            HEADER,
+            VERSION,
+            self.level.to_line(),
+            self.block_size,
+            self.seed
+        );
+
+        let mut i = 0;
+        while i < self.ticks.len() {
+            let run = self.ticks[i..].iter().take_while(|&input| *input == self.ticks[i]).count();
+            text += format!("{} {}", run, self.ticks[i].to_line()).trim_end();
+            text.push('\n');
+            i += run;
+        }
+
+        text
+    }
+
+    pub fn parse(contents: &str) -> Result<Replay, String> {
+        let mut lines = contents.lines().enumerate();
+
+        let version = lines
+            .next()
+            .and_then(|(_, line)| line.strip_prefix(HEADER))
+            .ok_or_else(|| "not a replay file".to_string())?;
+        if version.trim() != VERSION.to_string() {
+            return Err(format!("unsupported replay version {}", version.trim()));
+        }
+
+        let mut level = None;
+        let mut block_size = None;
+        let mut seed = None;
+        for (number, line) in lines.by_ref() {
+            let invalid = || format!("line {}: invalid '{}'", number + 1, line);
+            match line.split_once(' ') {
+                Some(("level", source)) => level = Some(LevelSource::parse_line(source).ok_or_else(invalid)?),
+                Some(("block_size", value)) => block_size = Some(value.parse().map_err(|_| invalid())?),
+                Some(("seed", value)) => seed = Some(value.parse().map_err(|_| invalid())?),
+                None if line == "ticks" => break,
+                _ => return Err(invalid()),
+            }
+        }
+
+        let mut replay = Replay::new(
+            level.ok_or("missing level")?,
+            block_size.ok_or("missing block_size")?,
+            seed.ok_or("missing seed")?,
+        );
+        for (number, line) in lines {
+            let invalid = || format!("line {}: invalid '{}'", number + 1, line);
+            let (count, input) = line.split_once(' ').unwrap_or((line, ""));
+            let count: usize = count.parse().map_err(|_| invalid())?;
+            let input = InputState::parse_line(input).ok_or_else(invalid)?;
+            replay.ticks.extend(std::iter::repeat_n(input, count));
+        }
+
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Replay::parse(&contents)
+    }
+
+    /// Plays the whole replay on a fresh world without rendering. Stops early
+    /// if the run ends before the recorded input does.
+    pub fn simulate(&self, level: &Level) -> (World, Vec<WorldEvent>) {
+        let mut world = World::new(level, self.block_size, self.seed);
+        let mut events = Vec::new();
+
+        for input in &self.ticks {
+            events.extend(world.update(TICK, input));
+            if events.iter().any(|event| matches!(event, WorldEvent::Won | WorldEvent::GameOver)) {
+                break;
+            }
+        }
+
+        (world, events)
+    }
+}
+
+/// Feeds recorded input back to the game one tick at a time.
+pub struct Playback {
+    pub replay: Replay,
+    tick: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback { replay, tick: 0 }
+    }
+
+    /// Input for the next tick, or `None` once the recording is over.
+    pub fn next_input(&mut self) -> Option<&InputState> {
+        let input = self.replay.ticks.get(self.tick)?;
+        self.tick += 1;
+        Some(input)
+    }
+
+    pub fn restart(&mut self) {
+        self.tick = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Action, DeviceState, InputBindings};
+    use crate::level::parse_level;
+    use raylib::consts::KeyboardKey::{self, *};
+
+    const LEVEL: &str = "+-------+\n|       |\n| +---+ |\n|       |\n+-------+\n\nplayer 1 1 0\nprincess 7 3\nenemy 4 3 random 1,3 4,3 7,3 7,1\n";
+
+    fn hold(keys: &[KeyboardKey], previous: &InputState) -> InputState {
+        let devices = DeviceState { keys: keys.iter().copied().collect(), gamepad: None };
+        InputState::resolve(&InputBindings::defaults(), &devices, previous)
+    }
+
+    /// A made-up run: walk, turn, swing now and then.
+    fn scripted_run(level: &Level, seed: u64) -> (Replay, World) {
+        let mut replay = Replay::new(LevelSource::File("test".to_string()), 100, seed);
+        let mut world = World::new(level, 100, seed);
+        let mut input = InputState::default();
+
+        for tick in 0..900 {
+            let keys: &[KeyboardKey] = match tick % 300 {
+                0..=119 => &[KEY_W],
+                120..=149 => &[KEY_D, KEY_SPACE],
+                150..=249 => &[KEY_W, KEY_LEFT_SHIFT],
+                _ => &[KEY_A],
+            };
+            input = hold(keys, &input);
+            replay.record(&input);
+            world.update(TICK, &input);
+        }
+
+        (replay, world)
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let level = parse_level("test", LEVEL);
+        let (replay, _) = scripted_run(&level, 7);
+
+        let text = replay.to_text();
+        assert!(text.lines().count() < 30, "runs of equal input share a line");
+        assert_eq!(Replay::parse(&text), Ok(replay));
+        assert!(Replay::parse("knight-maze replay 99\n").is_err());
+    }
+
+    #[test]
+    fn replay_reproduces_the_run() {
+        let level = parse_level("test", LEVEL);
+        let (replay, original) = scripted_run(&level, 7);
+
+        let replay = Replay::parse(&replay.to_text()).unwrap();
+        let (world, _) = replay.simulate(&level);
+
+        assert_eq!(world.player.pos, original.player.pos);
+        assert_eq!(world.player.a, original.player.a);
+        assert_eq!(world.player.health, original.player.health);
+        let positions = |world: &World| world.enemies.iter().map(|enemy| (enemy.pos(), enemy.state)).collect::<Vec<_>>();
+        assert_eq!(positions(&world), positions(&original));
+    }
+
+    #[test]
+    fn partial_values_and_presses_survive() {
+        let line = "move_forward turn_left=0.5 attack!";
+        let input = InputState::parse_line(line).unwrap();
+        assert_eq!(input.value(Action::TurnLeft), 0.5);
+        assert!(input.is_pressed(Action::Attack));
+        assert!(!input.is_pressed(Action::MoveForward));
+        assert_eq!(input.to_line(), line);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::inventory::Item;
+use crate::level::LevelSource;
+use crate::settings::config_path;
+use crate::world::World;
+
+/// Time bonus for an instant finish; it drains away over five minutes.
+const TIME_BONUS: u32 = 3000;
+const TIME_PENALTY_PER_SECOND: f32 = 10.0;
+const LIFE_BONUS: u32 = 1000;
+/// For every goblin that never saw the player.
+const STEALTH_BONUS: u32 = 250;
+const KILL_BONUS: u32 = 100;
+const COIN_BONUS: u32 = 50;
+/// Entries kept per level.
+pub const TABLE_SIZE: usize = 5;
+pub const NAME_LENGTH: usize = 12;
+
+/// `m:ss`, for timers and run summaries.
+pub fn format_time(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Points for a run, split by what earned them. Time and lives count on
+/// every run; sneaking past goblins, slaying them and collecting coins are
+/// optional objectives.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Score {
+    pub time: u32,
+    pub lives: u32,
+    pub stealth: u32,
+    pub kills: u32,
+    pub coins: u32,
+}
+
+impl Score {
+    /// Score of the run so far; `enemies` is how many the level started with.
+    pub fn new(world: &World, enemies: usize) -> Self {
+        let penalty = (world.elapsed * TIME_PENALTY_PER_SECOND) as u32;
+        let unseen = (enemies as u32).saturating_sub(world.stats.spotted_by);
+        Score {
+            time: TIME_BONUS.saturating_sub(penalty),
+            lives: world.player.lives * LIFE_BONUS,
+            stealth: unseen * STEALTH_BONUS,
+            kills: world.stats.kills * KILL_BONUS,
+            coins: world.player.inventory.count(Item::Coin) * COIN_BONUS,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.time + self.lives + self.stealth + self.kills + self.coins
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub time: f32,
+}
+
+/// Best runs per level, kept between sessions. Generated mazes get a table
+/// per size and seed, since each seed is a different maze.
+///
+/// The file has a `[LEVEL]` line per table, where LEVEL is the level's
+/// source line, followed by `SCORE SECONDS NAME` lines.
+#[derive(Default, PartialEq, Debug)]
+pub struct HighScores {
+    tables: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScores {
+    pub fn path() -> String {
+        config_path("highscores.cfg")
+    }
+
+    /// A missing file just means no scores yet.
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => HighScores::parse(path, &contents),
+            Err(_) => HighScores::default(),
+        }
+    }
+
+    fn parse(name: &str, contents: &str) -> Self {
+        let mut scores = HighScores::default();
+        let mut table = None;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(level) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                table = Some(scores.tables.entry(level.to_string()).or_default());
+                continue;
+            }
+
+            match (parse_entry(line), &mut table) {
+                (Some(entry), Some(table)) => table.push(entry),
+                _ => eprintln!("{}: ignoring malformed line '{}'", name, line),
+            }
+        }
+        scores
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (level, table) in &self.tables {
+            text += &format!("[{}]\n", level);
+            for entry in table {
+                text += &format!("{} {} {}\n", entry.score, entry.time, entry.name);
+            }
+        }
+        text
+    }
+
+    pub fn save(&self, path: &str) {
+        if let Some(dir) = Path::new(path).parent()
+            && let Err(err) = fs::create_dir_all(dir)
+        {
+            eprintln!("Failed to create {}: {}", dir.display(), err);
+        }
+        if let Err(err) = fs::write(path, self.to_text()) {
+            eprintln!("Failed to save high scores to {}: {}", path, err);
+        }
+    }
+
+    /// Best first.
+    pub fn table(&self, level: &LevelSource) -> &[HighScore] {
+        self.tables.get(&level.to_line()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `score` would make it into the level's table.
+    pub fn qualifies(&self, level: &LevelSource, score: u32) -> bool {
+        let table = self.table(level);
+        score > 0 && (table.len() < TABLE_SIZE || table.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds a run and returns its place in the table, if it stayed in.
+    /// Equal scores rank the faster run first.
+    pub fn insert(&mut self, level: &LevelSource, entry: HighScore) -> Option<usize> {
+        let table = self.tables.entry(level.to_line()).or_default();
+        let rank = table
+            .iter()
+            .position(|other| entry.score > other.score || (entry.score == other.score && entry.time < other.time))
+            .unwrap_or(table.len());
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        (rank < TABLE_SIZE).then_some(rank)
+    }
+}
+
+fn parse_entry(line: &str) -> Option<HighScore> {
+    let mut words = line.splitn(3, ' ');
+    Some(HighScore {
+        score: words.next()?.parse().ok()?,
+        time: words.next()?.parse().ok()?,
+        name: words.next()?.to_string(),
+    })
+}
+
+/// Adds a typed character to a name being entered, skipping anything that
+/// wouldn't survive the high-score file.
+pub fn type_name(name: &mut String, typed: char) {
+    if (typed.is_alphanumeric() || typed == ' ' || typed == '-') && name.chars().count() < NAME_LENGTH {
+        name.push(typed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32, time: f32) -> HighScore {
+        HighScore { name: name.to_string(), score, time }
+    }
+
+    #[test]
+    fn tables_keep_the_best_runs_per_level() {
+        let level = LevelSource::File("levels/level1.txt".to_string());
+        let maze = LevelSource::Generated { cols: 8, rows: 6, seed: 42 };
+        let mut scores = HighScores::default();
+
+        for score in 1..=TABLE_SIZE as u32 {
+            assert!(scores.qualifies(&level, score * 100));
+            scores.insert(&level, entry("Ana", score * 100, 60.0));
+        }
+        assert!(!scores.qualifies(&level, 100));
+        assert_eq!(scores.insert(&level, entry("Bo", 300, 30.0)), Some(2));
+        assert_eq!(scores.insert(&level, entry("Cy", 50, 10.0)), None);
+
+        let table = scores.table(&level);
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[0].score, 500);
+        assert_eq!(table[2].name, "Bo");
+        assert!(scores.table(&maze).is_empty());
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut scores = HighScores::default();
+        scores.insert(&LevelSource::File("levels/level1.txt".to_string()), entry("Sir Lancelot", 4200, 83.5));
+        scores.insert(&LevelSource::Generated { cols: 8, rows: 6, seed: 42 }, entry("Ana", 3100, 120.25));
+
+        assert_eq!(HighScores::parse("test", &scores.to_text()), scores);
+    }
+
+    #[test]
+    fn names_are_limited() {
+        let mut name = String::new();
+        for typed in "Sir Lancelot]\tof the Lake".chars() {
+            type_name(&mut name, typed);
+        }
+        assert_eq!(name, "Sir Lancelot");
+    }
+}
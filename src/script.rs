@@ -0,0 +1,380 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Component, Path};
+use std::rc::Rc;
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, ImmutableString, Map, Scope, AST, FLOAT, INT};
+
+use crate::enemy::HEALTH;
+use crate::hazard::occupied_cells;
+use crate::inventory::Item;
+use crate::maze::{cell_at, is_walkable, Maze};
+use crate::pathfinding::Cell;
+use crate::world::World;
+
+/// Cells a script may put into the maze: floor, walls, doors and hazards.
+const SETTABLE_CELLS: &str = " +-|#_DRGB^=~";
+/// Rhai operations one call may run before it is stopped, so a runaway loop
+/// can't hang the game.
+const MAX_OPERATIONS: u64 = 50_000;
+
+/// Something a script asked the world to do. Calls only queue these; the
+/// world carries them out once the script returns. Goblins are given by id.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Message(String),
+    Sound(String),
+    Music(String),
+    SetCell(Cell, char),
+    SpawnGoblin(Cell),
+    SetGoblinHealth(usize, u32),
+    SetGoblinSize(usize, f32),
+    HurtPlayer,
+    HealPlayer,
+    Give(Item),
+    Win,
+    GameOver,
+}
+
+/// What a script can see of the world during a call, and what it has asked for.
+#[derive(Default)]
+struct Context {
+    elapsed: f32,
+    player: Cell,
+    health: u32,
+    lives: u32,
+    maze: Maze,
+    /// Cells with someone in them, where walls can't go.
+    occupied: Vec<Cell>,
+    /// Id, cell and health of each goblin, spawned ones included.
+    goblins: Vec<(usize, Cell, u32)>,
+    next_goblin_id: usize,
+    commands: Vec<Command>,
+}
+
+/// A level's Rhai script. The world calls its `on_start()` before the first
+/// tick, `on_tick()` on every tick and any function a `call` trigger names.
+/// Each call gets `this`, a map kept between calls and in saves, for the
+/// script's own state.
+///
+/// Scripts can read `elapsed()`, `player_col()`, `player_row()`,
+/// `player_health()`, `player_lives()`, `cell(col, row)`, `goblin_ids()`,
+/// `goblin_col(id)`, `goblin_row(id)` and `goblin_health(id)`, and act with
+/// `message(text)`, `play_sound(file)`, `play_music(file)`,
+/// `set_cell(col, row, cell)`, `spawn_goblin(col, row)` (returns its id),
+/// `set_goblin_health(id, health)`, `set_goblin_size(id, scale)`,
+/// `hurt_player()`, `heal_player()`, `give(item)`, `win()` and `game_over()`.
+/// There is no file or system access: scripts can't import modules or print,
+/// and can only name sound files inside `assets/`. Calls are limited in how
+/// long they can run.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    context: Rc<RefCell<Context>>,
+    pub state: Dynamic,
+}
+
+impl Script {
+    /// Compiles a script file. Levels do this once when they load, and each
+    /// world runs its own copy.
+    pub fn load(path: &str) -> Result<AST, String> {
+        let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        Script::compile(&source).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn compile(source: &str) -> Result<AST, String> {
+        sandbox().compile(source).map_err(|err| err.to_string())
+    }
+
+    pub fn new(ast: AST) -> Script {
+        let context = Rc::new(RefCell::new(Context::default()));
+        let engine = build_engine(&context);
+        Script { engine, ast, context, state: Dynamic::from_map(Map::new()) }
+    }
+
+    /// Calls a script function with no arguments and returns the commands it
+    /// queued. Functions the script doesn't define are skipped.
+    pub fn call(&mut self, name: &str, world: &World) -> Result<Vec<Command>, String> {
+        if !has_function(&self.ast, name) {
+            return Ok(Vec::new());
+        }
+
+        *self.context.borrow_mut() = Context {
+            elapsed: world.elapsed,
+            player: cell_at(world.player.pos, world.block_size),
+            health: world.player.health,
+            lives: world.player.lives,
+            maze: world.maze.clone(),
+            occupied: occupied_cells(&world.bodies(), world.block_size),
+            goblins: world.enemies.iter().map(|enemy| (enemy.id, cell_at(enemy.pos(), world.block_size), enemy.health)).collect(),
+            next_goblin_id: world.next_enemy_id,
+            commands: Vec::new(),
+        };
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, name, ());
+        let commands = std::mem::take(&mut self.context.borrow_mut().commands);
+        result.map(|_| commands).map_err(|err| format!("{}: {}", name, err))
+    }
+
+    /// `this` as a Rhai literal on one line, for saves. Only plain data can
+    /// be saved: `()`, bools, ints, finite floats, strings, arrays and maps.
+    pub fn state_text(&self) -> Result<String, String> {
+        literal(&self.state)
+    }
+
+    pub fn restore_state(&mut self, text: &str) -> Result<(), String> {
+        let state = self.engine.eval_expression::<Dynamic>(text).map_err(|err| err.to_string())?;
+        if !state.is_map() {
+            return Err(format!("expected a map, found {}", state.type_name()));
+        }
+        self.state = state;
+        Ok(())
+    }
+}
+
+fn literal(value: &Dynamic) -> Result<String, String> {
+    if value.is_unit() {
+        Ok("()".to_string())
+    } else if let Ok(value) = value.as_bool() {
+        Ok(value.to_string())
+    } else if let Ok(value) = value.as_int() {
+        Ok(value.to_string())
+    } else if let Ok(value) = value.as_float() {
+        // Debug output always reads back as the same float.
+        match value.is_finite() {
+            true => Ok(format!("{:?}", value)),
+            false => Err(format!("can't save the float {}", value)),
+        }
+    } else if value.is_string() {
+        Ok(quote(&value.read_lock::<ImmutableString>().unwrap()))
+    } else if value.is_array() {
+        let items = value.read_lock::<Array>().unwrap().iter().map(literal).collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("[{}]", items.join(", ")))
+    } else if value.is_map() {
+        let entries = value
+            .read_lock::<Map>()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| Ok(format!("{}: {}", quote(key), literal(value)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(format!("#{{{}}}", entries.join(", ")))
+    } else {
+        Err(format!("can't save a {}", value.type_name()))
+    }
+}
+
+/// A Rhai string literal, escaped to stay on one line.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\r' => quoted += "\\r",
+            '\t' => quoted += "\\t",
+            c if c.is_control() => quoted += &format!("\\U{:08X}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Whether a script defines a function with no parameters called `name`.
+pub fn has_function(ast: &AST, name: &str) -> bool {
+    ast.iter_functions().any(|function| function.name == name && function.params.is_empty())
+}
+
+/// An engine with no file or system access and limits on what a call can do.
+fn sandbox() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1_000);
+    engine.set_max_array_size(1_000);
+    engine.set_max_map_size(1_000);
+    engine.disable_symbol("eval");
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine
+}
+
+fn build_engine(context: &Rc<RefCell<Context>>) -> Engine {
+    let mut engine = sandbox();
+
+    let ctx = context.clone();
+    engine.register_fn("elapsed", move || ctx.borrow().elapsed as FLOAT);
+    let ctx = context.clone();
+    engine.register_fn("player_col", move || ctx.borrow().player.0 as INT);
+    let ctx = context.clone();
+    engine.register_fn("player_row", move || ctx.borrow().player.1 as INT);
+    let ctx = context.clone();
+    engine.register_fn("player_health", move || ctx.borrow().health as INT);
+    let ctx = context.clone();
+    engine.register_fn("player_lives", move || ctx.borrow().lives as INT);
+    let ctx = context.clone();
+    engine.register_fn("cell", move |col: INT, row: INT| maze_cell(&ctx.borrow().maze, col, row).map_or(String::new(), String::from));
+    let ctx = context.clone();
+    engine.register_fn("goblin_ids", move || ctx.borrow().goblins.iter().map(|&(id, _, _)| Dynamic::from(id as INT)).collect::<Array>());
+    let ctx = context.clone();
+    engine.register_fn("goblin_col", move |id: INT| goblin(&ctx.borrow().goblins, id).map_or(-1, |(_, (col, _), _)| col as INT));
+    let ctx = context.clone();
+    engine.register_fn("goblin_row", move |id: INT| goblin(&ctx.borrow().goblins, id).map_or(-1, |(_, (_, row), _)| row as INT));
+    let ctx = context.clone();
+    engine.register_fn("goblin_health", move |id: INT| goblin(&ctx.borrow().goblins, id).map_or(0, |(_, _, health)| health as INT));
+
+    let queue = |context: &Rc<RefCell<Context>>, command: fn(&str) -> Option<Command>| {
+        let context = context.clone();
+        move |text: &str| {
+            if let Some(command) = command(text) {
+                context.borrow_mut().commands.push(command);
+            }
+        }
+    };
+    engine.register_fn("message", queue(context, |text| Some(Command::Message(text.to_string()))));
+    engine.register_fn("play_sound", queue(context, |file| asset_path(file).map(Command::Sound)));
+    engine.register_fn("play_music", queue(context, |file| asset_path(file).map(Command::Music)));
+    engine.register_fn("give", queue(context, |item| Item::from_name(item).map(Command::Give)));
+
+    let send = |context: &Rc<RefCell<Context>>, command: Command| {
+        let context = context.clone();
+        move || context.borrow_mut().commands.push(command.clone())
+    };
+    engine.register_fn("hurt_player", send(context, Command::HurtPlayer));
+    engine.register_fn("heal_player", send(context, Command::HealPlayer));
+    engine.register_fn("win", send(context, Command::Win));
+    engine.register_fn("game_over", send(context, Command::GameOver));
+
+    let ctx = context.clone();
+    engine.register_fn("set_cell", move |col: INT, row: INT, cell: &str| {
+        let mut context = ctx.borrow_mut();
+        let mut chars = cell.chars();
+        let (Some(cell), None) = (chars.next(), chars.next()) else {
+            return;
+        };
+        if !SETTABLE_CELLS.contains(cell) || maze_cell(&context.maze, col, row).is_none() {
+            return;
+        }
+        let (col, row) = (col as usize, row as usize);
+        // The world won't put a wall on top of anyone either.
+        if is_walkable(cell) || !context.occupied.contains(&(col, row)) {
+            context.maze[row][col] = cell;
+            context.commands.push(Command::SetCell((col, row), cell));
+        }
+    });
+    let ctx = context.clone();
+    engine.register_fn("spawn_goblin", move |col: INT, row: INT| {
+        let mut context = ctx.borrow_mut();
+        if !maze_cell(&context.maze, col, row).is_some_and(is_walkable) {
+            return -1;
+        }
+        // The world hands out ids in the same order.
+        let (id, cell) = (context.next_goblin_id, (col as usize, row as usize));
+        context.next_goblin_id += 1;
+        context.goblins.push((id, cell, HEALTH));
+        context.occupied.push(cell);
+        context.commands.push(Command::SpawnGoblin(cell));
+        id as INT
+    });
+    let ctx = context.clone();
+    engine.register_fn("set_goblin_health", move |id: INT, health: INT| {
+        let mut context = ctx.borrow_mut();
+        let health = health.max(0) as u32;
+        if let Some(goblin) = context.goblins.iter_mut().find(|goblin| goblin.0 as INT == id) {
+            goblin.2 = health;
+            context.commands.push(Command::SetGoblinHealth(id as usize, health));
+        }
+    });
+    let ctx = context.clone();
+    engine.register_fn("set_goblin_size", move |id: INT, scale: FLOAT| {
+        let mut context = ctx.borrow_mut();
+        if goblin(&context.goblins, id).is_some() {
+            context.commands.push(Command::SetGoblinSize(id as usize, scale.clamp(0.25, 3.0) as f32));
+        }
+    });
+
+    engine
+}
+
+fn maze_cell(maze: &Maze, col: INT, row: INT) -> Option<char> {
+    let (col, row) = (usize::try_from(col).ok()?, usize::try_from(row).ok()?);
+    maze.get(row)?.get(col).copied()
+}
+
+/// `file` if it names something inside `assets/`, without leaving it.
+fn asset_path(file: &str) -> Option<String> {
+    let path = Path::new(file);
+    let inside = path.starts_with("assets") && path.components().all(|component| matches!(component, Component::Normal(_)));
+    inside.then(|| file.to_string())
+}
+
+fn goblin(goblins: &[(usize, Cell, u32)], id: INT) -> Option<(usize, Cell, u32)> {
+    goblins.iter().find(|goblin| goblin.0 as INT == id).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::parse_level;
+
+    #[test]
+    fn scripts_queue_commands_and_keep_state() {
+        let level = parse_level("test", "+----+\n|    |\n+----+\n\nplayer 1 1 0\nprincess 4 1\n");
+        let world = World::new(&level, 100, 0);
+        let source = r##"
+            fn on_tick() {
+                this.ticks += 1;
+                if this.ticks == 2 {
+                    message("cell " + cell(player_col() + 1, player_row()) + "!");
+                    set_cell(2, 1, "#");
+                    set_cell(9, 9, "#");
+                    set_cell(1, 1, "#");
+                    message("under " + cell(1, 1));
+                    set_goblin_size(spawn_goblin(3, 1), 2.0);
+                    give("red_key");
+                }
+            }
+            fn on_start() { this.ticks = 0; this.notes = ["say \"hi\"\n\\", 1.5e-7, (), #{"odd key": false}]; }
+            fn remember() { this.next = Fn("on_tick"); }
+            fn forever() { loop {} }
+            fn wander() {
+                play_sound("../secret.wav");
+                play_sound("/etc/passwd");
+                play_music("assets/../../secret.wav");
+                print("hello");
+            }
+            fn sneak() { import "secret" as secret; }
+        "##;
+        let mut script = Script::new(Script::compile(source).unwrap());
+
+        assert_eq!(script.call("on_start", &world), Ok(Vec::new()));
+        assert_eq!(script.call("on_tick", &world), Ok(Vec::new()));
+        let commands = script.call("on_tick", &world).unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                Command::Message("cell  !".to_string()),
+                Command::SetCell((2, 1), '#'),
+                Command::Message("under  ".to_string()),
+                Command::SpawnGoblin((3, 1)),
+                Command::SetGoblinSize(0, 2.0),
+                Command::Give(Item::Key(crate::inventory::KeyColor::Red)),
+            ]
+        );
+        assert!(script.call("forever", &world).is_err());
+        assert_eq!(script.call("wander", &world), Ok(Vec::new()));
+        assert!(script.call("sneak", &world).is_err());
+
+        let mut restored = Script::new(Script::compile(source).unwrap());
+        restored.restore_state(&script.state_text().unwrap()).unwrap();
+        assert_eq!(restored.state_text(), script.state_text());
+        assert_eq!(restored.state.to_string(), script.state.to_string());
+        script.call("remember", &world).unwrap();
+        assert!(script.state_text().is_err());
+        assert_eq!(script.call("missing", &world), Ok(Vec::new()));
+    }
+}
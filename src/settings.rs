@@ -0,0 +1,323 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::input::{Action, InputState};
+use crate::minimap::MinimapCorner;
+
+const RESOLUTIONS: [(i32, i32); 5] = [(1024, 768), (1280, 720), (1300, 900), (1600, 900), (1920, 1080)];
+const FOVS: [f32; 8] = [50.0, 55.0, 60.0, 65.0, 70.0, 80.0, 90.0, 100.0];
+const TARGET_FPS: [u32; 4] = [30, 60, 120, 144];
+const VOLUMES: [f32; 11] = [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0];
+const MINIMAP_SIZES: [u32; 4] = [160, 220, 280, 340];
+const RENDER_SCALES: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+
+/// Directory for the game's config files: `$XDG_CONFIG_HOME/knight-maze`,
+/// `~/.config/knight-maze` or `%APPDATA%\knight-maze`.
+pub fn config_dir() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("knight-maze")
+}
+
+pub fn config_path(file: &str) -> String {
+    config_dir().join(file).to_string_lossy().into_owned()
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub width: i32,
+    pub height: i32,
+    pub fov: f32,
+    pub target_fps: u32,
+    pub volume: f32,
+    pub minimap: bool,
+    /// Draws enemies the player has seen on the minimap.
+    pub minimap_enemies: bool,
+    /// Minimap width and height in pixels.
+    pub minimap_size: u32,
+    pub minimap_corner: MinimapCorner,
+    /// Fraction of screen columns the raycaster casts a ray for.
+    pub render_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: 1300,
+            height: 900,
+            fov: 60.0,
+            target_fps: 60,
+            volume: 1.0,
+            minimap: true,
+            minimap_enemies: false,
+            minimap_size: 220,
+            minimap_corner: MinimapCorner::TopRight,
+            render_scale: 1.0,
+        }
+    }
+}
+
+/// Moves `current` one step through `options`, wrapping around. Values not
+/// in the list start from the first entry.
+fn cycle<T: PartialEq + Copy>(options: &[T], current: T, forward: bool) -> T {
+    let len = options.len();
+    let next = match options.iter().position(|&option| option == current) {
+        Some(i) if forward => (i + 1) % len,
+        Some(i) => (i + len - 1) % len,
+        None => 0,
+    };
+    options[next]
+}
+
+impl Settings {
+    pub fn path() -> String {
+        config_path("settings.cfg")
+    }
+
+    /// Reads `key = value` lines on top of the defaults. A missing file just
+    /// means defaults.
+    pub fn load(path: &str) -> Self {
+        let mut settings = Settings::default();
+        let Ok(contents) = fs::read_to_string(path) else {
+            return settings;
+        };
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("{}: ignoring malformed line '{}'", path, line);
+                continue;
+            };
+            if settings.set(key.trim(), value.trim()).is_none() {
+                eprintln!("{}: invalid setting '{}'", path, line);
+            }
+        }
+
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "resolution" => {
+                let (width, height) = value.split_once('x')?;
+                self.width = width.trim().parse().ok().filter(|&width| width > 0)?;
+                self.height = height.trim().parse().ok().filter(|&height| height > 0)?;
+            }
+            "fov" => self.fov = value.parse().ok().filter(|fov| (20.0..=160.0).contains(fov))?,
+            "target_fps" => self.target_fps = value.parse().ok().filter(|&fps| fps > 0)?,
+            "volume" => self.volume = value.parse().ok().filter(|volume| (0.0..=1.0).contains(volume))?,
+            "minimap" => self.minimap = value.parse().ok()?,
+            "minimap_enemies" => self.minimap_enemies = value.parse().ok()?,
+            "minimap_size" => self.minimap_size = value.parse().ok().filter(|&size| size > 0)?,
+            "minimap_corner" => self.minimap_corner = MinimapCorner::from_name(value)?,
+            "render_scale" => self.render_scale = value.parse().ok().filter(|scale| *scale > 0.0 && *scale <= 1.0)?,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    pub fn save(&self, path: &str) {
+        let contents = format!(
+            "resolution = {}x{}\nfov = {}\ntarget_fps = {}\nvolume = {}\nminimap = {}\nminimap_enemies = {}\nminimap_size = {}\nminimap_corner = {}\nrender_scale = {}\n",
+            self.width,
+            self.height,
+            self.fov,
+            self.target_fps,
+            self.volume,
+            self.minimap,
+            self.minimap_enemies,
+            self.minimap_size,
+            self.minimap_corner.name(),
+            self.render_scale
+        );
+
+        if let Some(dir) = PathBuf::from(path).parent()
+            && let Err(err) = fs::create_dir_all(dir)
+        {
+            eprintln!("Failed to create {}: {}", dir.display(), err);
+        }
+        if let Err(err) = fs::write(path, contents) {
+            eprintln!("Failed to save settings to {}: {}", path, err);
+        }
+    }
+
+    pub fn fov_radians(&self) -> f32 {
+        self.fov.to_radians()
+    }
+}
+
+/// Rows of the settings screen, in display order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SettingsEntry {
+    Resolution,
+    Fov,
+    TargetFps,
+    Volume,
+    Minimap,
+    MinimapEnemies,
+    MinimapSize,
+    MinimapCorner,
+    RenderScale,
+    Controls,
+    Back,
+}
+
+impl SettingsEntry {
+    pub const ALL: [SettingsEntry; 11] = [
+        SettingsEntry::Resolution,
+        SettingsEntry::Fov,
+        SettingsEntry::TargetFps,
+        SettingsEntry::Volume,
+        SettingsEntry::Minimap,
+        SettingsEntry::MinimapEnemies,
+        SettingsEntry::MinimapSize,
+        SettingsEntry::MinimapCorner,
+        SettingsEntry::RenderScale,
+        SettingsEntry::Controls,
+        SettingsEntry::Back,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingsEntry::Resolution => "Resolution",
+            SettingsEntry::Fov => "Field of view",
+            SettingsEntry::TargetFps => "Target FPS",
+            SettingsEntry::Volume => "Volume",
+            SettingsEntry::Minimap => "Minimap",
+            SettingsEntry::MinimapEnemies => "Minimap enemies",
+            SettingsEntry::MinimapSize => "Minimap size",
+            SettingsEntry::MinimapCorner => "Minimap corner",
+            SettingsEntry::RenderScale => "Render scale",
+            SettingsEntry::Controls => "Controls",
+            SettingsEntry::Back => "Back",
+        }
+    }
+
+    pub fn value(&self, settings: &Settings) -> String {
+        match self {
+            SettingsEntry::Resolution => format!("{}x{}", settings.width, settings.height),
+            SettingsEntry::Fov => settings.fov.to_string(),
+            SettingsEntry::TargetFps => settings.target_fps.to_string(),
+            SettingsEntry::Volume => format!("{}%", (settings.volume * 100.0).round()),
+            SettingsEntry::Minimap => if settings.minimap { "On" } else { "Off" }.to_string(),
+            SettingsEntry::MinimapEnemies => if settings.minimap_enemies { "On" } else { "Off" }.to_string(),
+            SettingsEntry::MinimapSize => settings.minimap_size.to_string(),
+            SettingsEntry::MinimapCorner => settings.minimap_corner.label().to_string(),
+            SettingsEntry::RenderScale => format!("{}%", (settings.render_scale * 100.0).round()),
+            SettingsEntry::Controls | SettingsEntry::Back => String::new(),
+        }
+    }
+
+    fn adjust(&self, settings: &mut Settings, forward: bool) {
+        match self {
+            SettingsEntry::Resolution => {
+                (settings.width, settings.height) = cycle(&RESOLUTIONS, (settings.width, settings.height), forward);
+            }
+            SettingsEntry::Fov => settings.fov = cycle(&FOVS, settings.fov, forward),
+            SettingsEntry::TargetFps => settings.target_fps = cycle(&TARGET_FPS, settings.target_fps, forward),
+            SettingsEntry::Volume => settings.volume = cycle(&VOLUMES, settings.volume, forward),
+            SettingsEntry::Minimap => settings.minimap = !settings.minimap,
+            SettingsEntry::MinimapEnemies => settings.minimap_enemies = !settings.minimap_enemies,
+            SettingsEntry::MinimapSize => settings.minimap_size = cycle(&MINIMAP_SIZES, settings.minimap_size, forward),
+            SettingsEntry::MinimapCorner => settings.minimap_corner = cycle(&MinimapCorner::ALL, settings.minimap_corner, forward),
+            SettingsEntry::RenderScale => settings.render_scale = cycle(&RENDER_SCALES, settings.render_scale, forward),
+            SettingsEntry::Controls | SettingsEntry::Back => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SettingsEvent {
+    Changed,
+    OpenControls,
+    Close,
+}
+
+/// Settings screen cursor. Left/right or confirm change the highlighted
+/// value; the caller applies and saves the result.
+#[derive(Default)]
+pub struct SettingsMenu {
+    pub selected: usize,
+}
+
+impl SettingsMenu {
+    pub fn update(&mut self, input: &InputState, settings: &mut Settings) -> Option<SettingsEvent> {
+        let len = SettingsEntry::ALL.len();
+        if input.is_pressed(Action::MenuUp) {
+            self.selected = (self.selected + len - 1) % len;
+        }
+        if input.is_pressed(Action::MenuDown) {
+            self.selected = (self.selected + 1) % len;
+        }
+        if input.is_pressed(Action::Back) {
+            return Some(SettingsEvent::Close);
+        }
+
+        let entry = SettingsEntry::ALL[self.selected];
+        let confirm = input.is_pressed(Action::Confirm);
+        let forward = input.is_pressed(Action::MenuRight) || confirm;
+        let backward = input.is_pressed(Action::MenuLeft);
+
+        match entry {
+            SettingsEntry::Controls => confirm.then_some(SettingsEvent::OpenControls),
+            SettingsEntry::Back => confirm.then_some(SettingsEvent::Close),
+            _ if forward || backward => {
+                entry.adjust(settings, forward);
+                Some(SettingsEvent::Changed)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip_and_bad_lines_keep_defaults() {
+        let path = env::temp_dir().join("knight-maze-settings-test.cfg");
+        let path = path.to_string_lossy();
+
+        let settings = Settings {
+            width: 1600,
+            height: 900,
+            fov: 90.0,
+            volume: 0.5,
+            minimap: false,
+            minimap_enemies: true,
+            minimap_size: 160,
+            minimap_corner: MinimapCorner::BottomLeft,
+            ..Settings::default()
+        };
+        settings.save(&path);
+        assert_eq!(Settings::load(&path), settings);
+
+        fs::write(&*path, "fov = 500
+volume = loud
+render_scale = 0.5
+minimap_corner = middle
+").unwrap();
+        let loaded = Settings::load(&path);
+        assert_eq!(loaded.fov, Settings::default().fov);
+        assert_eq!(loaded.volume, Settings::default().volume);
+        assert_eq!(loaded.render_scale, 0.5);
+        assert_eq!(loaded.minimap_corner, Settings::default().minimap_corner);
+
+        fs::remove_file(&*path).unwrap();
+    }
+
+    #[test]
+    fn cycling_wraps_both_ways() {
+        assert_eq!(cycle(&TARGET_FPS, 144, true), 30);
+        assert_eq!(cycle(&TARGET_FPS, 30, false), 144);
+        assert_eq!(cycle(&TARGET_FPS, 75, true), 30);
+    }
+}
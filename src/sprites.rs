@@ -7,6 +7,10 @@ pub struct Sprite {
     pub frame_height: u32,
     pub start_anim_x: u32,
     pub start_anim_y: u32,
+    /// Size relative to normal, shrinking towards the floor.
+    pub scale: f32,
+    /// Multiplied into every pixel, e.g. to flash red when hurt.
+    pub tint: Color,
 }
 
 impl Sprite {
@@ -18,6 +22,8 @@ impl Sprite {
             frame_height,
             start_anim_x,
             start_anim_y,
+            scale: 1.0,
+            tint: Color::WHITE,
         }
     }
 
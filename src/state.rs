@@ -0,0 +1,387 @@
+use crate::input::{Action, InputState};
+use crate::save::{QUICK_SLOT, SLOTS};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    LevelSelect,
+    Playing,
+    Paused,
+    /// Typing a name for a new high score, before the level-complete screen.
+    EnterName,
+    LevelComplete,
+    GameOver,
+    Credits,
+    Settings,
+    SaveGame,
+    LoadGame,
+}
+
+/// Work the game has to do because of a transition, beyond switching screens.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Effect {
+    /// Start over from the first level, or the one given on the command line.
+    NewGame,
+    /// Rebuild the world from the level: player, enemies and explored map.
+    ResetWorld,
+    /// Write the world to a save slot.
+    Save(usize),
+    /// Replace the world with a save slot. The game calls `start` if it works.
+    Load(usize),
+    /// Play the campaign level after the one just finished. The game calls
+    /// `start` once it is loaded.
+    NextLevel,
+    /// Play a campaign level picked from level select, if it is unlocked.
+    PlayLevel(usize),
+    /// Add the finished run to the high-score table under the entered name.
+    RecordScore,
+    Quit,
+}
+
+const MAIN_MENU: [&str; 6] = ["New game", "Select level", "Load game", "Settings", "Credits", "Quit"];
+const PAUSE_MENU: [&str; 5] = ["Resume", "Save game", "Settings", "Restart", "Quit to menu"];
+/// One entry per save slot, starting with the quick-save one, then Back.
+const SLOT_MENU: [&str; SLOTS + 1] = ["Quick save", "Slot 1", "Slot 2", "Slot 3", "Back"];
+const NEXT_LEVEL_MENU: [&str; 3] = ["Next level", "Play again", "Main menu"];
+const LEVEL_COMPLETE_MENU: [&str; 2] = ["Play again", "Main menu"];
+const GAME_OVER_MENU: [&str; 2] = ["Try again", "Main menu"];
+
+/// Which screen is showing, the highlighted menu entry and where the
+/// settings screen returns to. Transitions only look at `InputState`, so
+/// they can be driven without a window.
+#[derive(Default)]
+pub struct StateMachine {
+    pub state: GameState,
+    pub menu_index: usize,
+    /// Campaign levels listed by level select, which adds a Back entry.
+    pub levels: usize,
+    has_next_level: bool,
+    settings_return: GameState,
+}
+
+impl StateMachine {
+    /// Entries of the menu shown in the current state, if it has a fixed one.
+    /// Level select lists the campaign, which the game knows the names of.
+    pub fn menu(&self) -> &'static [&'static str] {
+        match self.state {
+            GameState::MainMenu => &MAIN_MENU,
+            GameState::Paused => &PAUSE_MENU,
+            GameState::LevelComplete if self.has_next_level => &NEXT_LEVEL_MENU,
+            GameState::LevelComplete => &LEVEL_COMPLETE_MENU,
+            GameState::GameOver => &GAME_OVER_MENU,
+            GameState::SaveGame | GameState::LoadGame => &SLOT_MENU,
+            GameState::LevelSelect | GameState::EnterName | GameState::Playing | GameState::Credits | GameState::Settings => &[],
+        }
+    }
+
+    fn entries(&self) -> usize {
+        match self.state {
+            GameState::LevelSelect => self.levels + 1,
+            _ => self.menu().len(),
+        }
+    }
+
+    fn go(&mut self, state: GameState) {
+        self.state = state;
+        self.menu_index = 0;
+    }
+
+    fn open_settings(&mut self) {
+        self.settings_return = self.state;
+        self.go(GameState::Settings);
+    }
+
+    /// Skips the main menu, e.g. when a replay starts straight away.
+    pub fn start(&mut self) {
+        self.go(GameState::Playing);
+    }
+
+    /// Leaves the settings screen for whichever screen opened it.
+    pub fn close_settings(&mut self) {
+        let state = self.settings_return;
+        self.go(state);
+    }
+
+    /// Whether a game is in progress behind the current screen.
+    pub fn in_game(&self) -> bool {
+        match self.state {
+            GameState::Playing | GameState::Paused | GameState::SaveGame => true,
+            GameState::Settings => self.settings_return == GameState::Paused,
+            _ => false,
+        }
+    }
+
+    pub fn player_died(&mut self) {
+        if self.state == GameState::Playing {
+            self.go(GameState::GameOver);
+        }
+    }
+
+    /// `has_next_level` offers to go straight on to the next campaign level;
+    /// `high_score` asks for a name first.
+    pub fn level_won(&mut self, has_next_level: bool, high_score: bool) {
+        if self.state == GameState::Playing {
+            self.has_next_level = has_next_level;
+            self.go(if high_score { GameState::EnterName } else { GameState::LevelComplete });
+        }
+    }
+
+    /// Applies menu navigation and the actions that leave the current state.
+    /// The settings screen handles its own input and calls `close_settings`.
+    pub fn update(&mut self, input: &InputState) -> Option<Effect> {
+        let entries = self.entries();
+        if entries > 0 {
+            if input.is_pressed(Action::MenuUp) {
+                self.menu_index = (self.menu_index + entries - 1) % entries;
+            }
+            if input.is_pressed(Action::MenuDown) {
+                self.menu_index = (self.menu_index + 1) % entries;
+            }
+        }
+        let confirm = input.is_pressed(Action::Confirm);
+
+        match self.state {
+            GameState::MainMenu if confirm => match self.menu_index {
+                0 => {
+                    self.go(GameState::Playing);
+                    return Some(Effect::NewGame);
+                }
+                1 => self.go(GameState::LevelSelect),
+                2 => self.go(GameState::LoadGame),
+                3 => self.open_settings(),
+                4 => self.go(GameState::Credits),
+                _ => return Some(Effect::Quit),
+            },
+            GameState::LevelSelect if confirm && self.menu_index < self.levels => return Some(Effect::PlayLevel(self.menu_index)),
+            GameState::LevelSelect if confirm || input.is_pressed(Action::Back) => self.go(GameState::MainMenu),
+            GameState::Playing if input.is_pressed(Action::Pause) => self.go(GameState::Paused),
+            GameState::Playing if input.is_pressed(Action::QuickSave) => return Some(Effect::Save(QUICK_SLOT)),
+            GameState::Playing if input.is_pressed(Action::QuickLoad) => return Some(Effect::Load(QUICK_SLOT)),
+            GameState::Paused if input.is_pressed(Action::Pause) || input.is_pressed(Action::Back) => {
+                self.go(GameState::Playing);
+            }
+            GameState::Paused if confirm => match self.menu_index {
+                0 => self.go(GameState::Playing),
+                1 => self.go(GameState::SaveGame),
+                2 => self.open_settings(),
+                3 => {
+                    self.go(GameState::Playing);
+                    return Some(Effect::ResetWorld);
+                }
+                _ => self.go(GameState::MainMenu),
+            },
+            GameState::SaveGame if confirm && self.menu_index < SLOTS => {
+                let slot = self.menu_index;
+                self.go(GameState::Paused);
+                return Some(Effect::Save(slot));
+            }
+            GameState::SaveGame if confirm || input.is_pressed(Action::Back) => self.go(GameState::Paused),
+            GameState::LoadGame if confirm && self.menu_index < SLOTS => return Some(Effect::Load(self.menu_index)),
+            GameState::LoadGame if confirm || input.is_pressed(Action::Back) => self.go(GameState::MainMenu),
+            GameState::EnterName if confirm => {
+                self.go(GameState::LevelComplete);
+                return Some(Effect::RecordScore);
+            }
+            GameState::LevelComplete if confirm && self.has_next_level => match self.menu_index {
+                0 => return Some(Effect::NextLevel),
+                1 => {
+                    self.go(GameState::Playing);
+                    return Some(Effect::ResetWorld);
+                }
+                _ => self.go(GameState::MainMenu),
+            },
+            GameState::LevelComplete | GameState::GameOver if confirm => match self.menu_index {
+                0 => {
+                    self.go(GameState::Playing);
+                    return Some(Effect::ResetWorld);
+                }
+                _ => self.go(GameState::MainMenu),
+            },
+            GameState::Credits if confirm || input.is_pressed(Action::Back) => self.go(GameState::MainMenu),
+            _ => {}
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{DeviceState, InputBindings};
+    use raylib::consts::KeyboardKey::{self, *};
+
+    fn press(keys: &[KeyboardKey]) -> InputState {
+        let devices = DeviceState { keys: keys.iter().copied().collect(), gamepad: None };
+        InputState::resolve(&InputBindings::defaults(), &devices, &InputState::default())
+    }
+
+    fn start_game() -> StateMachine {
+        let mut machine = StateMachine::default();
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::NewGame));
+        machine
+    }
+
+    #[test]
+    fn new_game_resets_the_world() {
+        let mut machine = StateMachine::default();
+
+        // The game rebuilds the world when it sees `NewGame`.
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::NewGame));
+        assert_eq!(machine.state, GameState::Playing);
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let mut machine = start_game();
+
+        machine.update(&press(&[KEY_P]));
+        assert_eq!(machine.state, GameState::Paused);
+
+        assert_eq!(machine.update(&press(&[KEY_P])), None);
+        assert_eq!(machine.state, GameState::Playing);
+    }
+
+    #[test]
+    fn restart_from_pause_menu() {
+        let mut machine = start_game();
+        machine.update(&press(&[KEY_P]));
+        machine.update(&press(&[KEY_UP]));
+        machine.update(&press(&[KEY_UP]));
+
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::ResetWorld));
+        assert_eq!(machine.state, GameState::Playing);
+    }
+
+    #[test]
+    fn game_over_can_retry_or_leave() {
+        let mut machine = start_game();
+        machine.player_died();
+        assert_eq!(machine.state, GameState::GameOver);
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::ResetWorld));
+        assert_eq!(machine.state, GameState::Playing);
+
+        machine.player_died();
+        machine.update(&press(&[KEY_DOWN]));
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), None);
+        assert_eq!(machine.state, GameState::MainMenu);
+    }
+
+    #[test]
+    fn level_complete_only_from_playing() {
+        let mut machine = StateMachine::default();
+        machine.level_won(false, false);
+        assert_eq!(machine.state, GameState::MainMenu);
+
+        let mut machine = start_game();
+        machine.level_won(false, false);
+        assert_eq!(machine.state, GameState::LevelComplete);
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::ResetWorld));
+    }
+
+    #[test]
+    fn next_level_is_offered_when_there_is_one() {
+        let mut machine = start_game();
+        machine.level_won(true, false);
+        assert_eq!(machine.menu()[0], "Next level");
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::NextLevel));
+        // Stays put until the game has loaded the level.
+        assert_eq!(machine.state, GameState::LevelComplete);
+
+        machine.update(&press(&[KEY_DOWN]));
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::ResetWorld));
+        assert_eq!(machine.state, GameState::Playing);
+    }
+
+    #[test]
+    fn high_scores_ask_for_a_name_first() {
+        let mut machine = start_game();
+        machine.level_won(false, true);
+        assert_eq!(machine.state, GameState::EnterName);
+        // Letters that are also menu keys don't move anything.
+        assert_eq!(machine.update(&press(&[KEY_S])), None);
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::RecordScore));
+        assert_eq!(machine.state, GameState::LevelComplete);
+    }
+
+    #[test]
+    fn level_select_lists_the_campaign() {
+        let mut machine = StateMachine { levels: 3, ..Default::default() };
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_ENTER]));
+        assert_eq!(machine.state, GameState::LevelSelect);
+
+        machine.update(&press(&[KEY_DOWN]));
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::PlayLevel(1)));
+        assert_eq!(machine.state, GameState::LevelSelect);
+
+        // Three levels, then Back.
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_DOWN]));
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), None);
+        assert_eq!(machine.state, GameState::MainMenu);
+    }
+
+    #[test]
+    fn settings_return_to_where_they_were_opened() {
+        let mut machine = StateMachine::default();
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_ENTER]));
+        assert_eq!(machine.state, GameState::Settings);
+        machine.close_settings();
+        assert_eq!(machine.state, GameState::MainMenu);
+
+        let mut machine = start_game();
+        machine.update(&press(&[KEY_P]));
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_ENTER]));
+        assert_eq!(machine.state, GameState::Settings);
+        assert!(machine.in_game());
+        machine.close_settings();
+        assert_eq!(machine.state, GameState::Paused);
+    }
+
+    #[test]
+    fn credits_and_quit_from_main_menu() {
+        let mut machine = StateMachine::default();
+        machine.update(&press(&[KEY_UP]));
+        machine.update(&press(&[KEY_UP]));
+        machine.update(&press(&[KEY_ENTER]));
+        assert_eq!(machine.state, GameState::Credits);
+        machine.update(&press(&[KEY_BACKSPACE]));
+        assert_eq!(machine.state, GameState::MainMenu);
+
+        machine.update(&press(&[KEY_UP]));
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::Quit));
+    }
+
+    #[test]
+    fn save_slots_from_pause_and_load_slots_from_main_menu() {
+        let mut machine = start_game();
+        assert_eq!(machine.update(&press(&[KEY_F5])), Some(Effect::Save(QUICK_SLOT)));
+        assert_eq!(machine.update(&press(&[KEY_F9])), Some(Effect::Load(QUICK_SLOT)));
+
+        machine.update(&press(&[KEY_P]));
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_ENTER]));
+        assert_eq!(machine.state, GameState::SaveGame);
+        machine.update(&press(&[KEY_DOWN]));
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::Save(1)));
+        assert_eq!(machine.state, GameState::Paused);
+
+        let mut machine = StateMachine::default();
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_DOWN]));
+        machine.update(&press(&[KEY_ENTER]));
+        assert_eq!(machine.state, GameState::LoadGame);
+        assert_eq!(machine.update(&press(&[KEY_ENTER])), Some(Effect::Load(QUICK_SLOT)));
+        // Stays put until the game says the slot loaded.
+        assert_eq!(machine.state, GameState::LoadGame);
+        machine.update(&press(&[KEY_BACKSPACE]));
+        assert_eq!(machine.state, GameState::MainMenu);
+    }
+}
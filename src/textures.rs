@@ -6,32 +6,55 @@ pub struct TextureManager {
     textures: HashMap<char, Texture2D>
 }
 
+const TEXTURE_FILES: [(char, &str); 23] = [
+    ('|', "assets/wall2.png"),
+    ('-', "assets/wall2.png"),
+    ('+', "assets/wall2.png"),
+    ('D', "assets/door.png"),
+    ('R', "assets/door_red.png"),
+    ('G', "assets/door_green.png"),
+    ('B', "assets/door_blue.png"),
+    ('#', "assets/shift_wall.png"),
+    ('^', "assets/spikes_down.png"),
+    ('A', "assets/spikes_up.png"),
+    ('=', "assets/plate.png"),
+    ('~', "assets/poison.png"),
+    ('s', "assets/sword.png"),
+    ('g', "assets/goblin.png"),
+    ('h', "assets/heart.png"),
+    ('k', "assets/key.png"),
+    ('c', "assets/coin.png"),
+    ('f', "assets/speed.png"),
+    ('i', "assets/invisibility.png"),
+    ('p', "assets/princess.png"),
+    ('t', "assets/start.png"),
+    ('w', "assets/youwin.png"),
+    ('o', "assets/gameover.png"),
+];
+
 impl TextureManager {
     pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
+        let mut manager = TextureManager::headless();
+
+        for (ch, path) in TEXTURE_FILES {
+            let texture = rl.load_texture(thread, path).expect(&format!("Failed to load texture {}", path));
+            manager.textures.insert(ch, texture);
+        }
+
+        manager
+    }
+
+    /// Only the CPU-side images, for rendering into the framebuffer without a
+    /// window. Menu screens need `new`.
+    pub fn headless() -> Self {
         let mut images = HashMap::new();
-        let mut textures = HashMap::new();
-
-        let texture_files = vec![
-            ('|', "assets/wall2.png"),
-            ('-', "assets/wall2.png"),
-            ('+', "assets/wall2.png"),
-            ('s', "assets/sword.png"),
-            ('g', "assets/goblin.png"),
-            ('h', "assets/heart.png"),
-            ('p', "assets/princess.png"),
-            ('t', "assets/start.png"),
-            ('w', "assets/youwin.png"),
-            ('o', "assets/gameover.png"),
-        ];
-
-        for (ch, path) in texture_files {
+
+        for (ch, path) in TEXTURE_FILES {
             let image = Image::load_image(path).expect(&format!("Failed to load image {}", path));
-            let texture = rl.load_texture(thread, path).expect(&format!("Failed to load texture {}", path));
             images.insert(ch, image);
-            textures.insert(ch, texture);
         }
 
-        TextureManager { images, textures }
+        TextureManager { images, textures: HashMap::new() }
     }
 
     pub fn get_pixel_color(&self, ch: char, tx: u32, ty: u32) -> Color {
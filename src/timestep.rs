@@ -0,0 +1,65 @@
+/// Simulation steps per second, independent of the rendering frame rate.
+pub const TICK_RATE: f32 = 120.0;
+pub const TICK: f32 = 1.0 / TICK_RATE;
+/// Longest frame the simulation catches up on. Anything beyond it (a stall,
+/// a dragged window) is dropped instead of running hundreds of ticks at once.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Whole ticks in `seconds`, for timers that count down once per tick.
+pub const fn ticks(seconds: f32) -> u32 {
+    (seconds * TICK_RATE + 0.5) as u32
+}
+
+/// Distance or angle moved in one tick at `per_second`.
+pub const fn per_tick(per_second: f32) -> f32 {
+    per_second / TICK_RATE
+}
+
+/// Accumulates rendered frame time and hands it out as fixed ticks.
+#[derive(Default)]
+pub struct FixedTimestep {
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Adds a frame's duration and returns how many ticks to simulate.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let steps = (self.accumulator / TICK) as u32;
+        self.accumulator -= steps as f32 * TICK;
+        steps
+    }
+
+    /// How far the leftover time is into the next tick, from 0 to 1, for
+    /// drawing between the last two simulated states.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / TICK).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(fps: f32, seconds: f32) -> u32 {
+        let mut timestep = FixedTimestep::default();
+        (0..(fps * seconds) as u32).map(|_| timestep.advance(1.0 / fps)).sum()
+    }
+
+    #[test]
+    fn tick_count_does_not_depend_on_frame_rate() {
+        for fps in [30.0, 60.0, 144.0, 240.0] {
+            let steps = run(fps, 2.0);
+            assert!((239..=240).contains(&steps), "{} fps ran {} ticks", fps, steps);
+        }
+    }
+
+    #[test]
+    fn leftover_time_becomes_alpha() {
+        let mut timestep = FixedTimestep::default();
+        assert_eq!(timestep.advance(TICK * 2.5), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 0.01);
+        // A long stall only catches up a quarter of a second.
+        assert_eq!(timestep.advance(10.0), 30);
+    }
+}
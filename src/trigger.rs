@@ -0,0 +1,56 @@
+use crate::pathfinding::Cell;
+
+/// What a trigger does when the player walks into it.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TriggerAction {
+    /// Shown on screen like a notice.
+    Message(String),
+    /// Opens the door in a cell without using up a key.
+    OpenDoor(Cell),
+    /// A goblin standing guard at each cell.
+    Spawn(Vec<Cell>),
+    Sound(String),
+    /// Replaces the music until the level is restarted.
+    Music(String),
+    /// Wins the level, as if the princess had been reached.
+    EndLevel,
+    /// Calls a function of the level's script.
+    Call(String),
+}
+
+/// An area of the maze that does something when the player walks into it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trigger {
+    /// Top-left and bottom-right cells, both inside the area.
+    pub area: (Cell, Cell),
+    /// Fires every time the player walks in, not just the first.
+    pub repeat: bool,
+    pub action: TriggerAction,
+}
+
+impl Trigger {
+    pub fn contains(&self, (col, row): Cell) -> bool {
+        let ((left, top), (right, bottom)) = self.area;
+        (left..=right).contains(&col) && (top..=bottom).contains(&row)
+    }
+
+    /// The trigger's cells, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = Cell> {
+        let ((left, top), (right, bottom)) = self.area;
+        (top..=bottom).flat_map(move |row| (left..=right).map(move |col| (col, row)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn areas_include_both_corners() {
+        let trigger = Trigger { area: ((2, 1), (3, 2)), repeat: false, action: TriggerAction::EndLevel };
+
+        assert!(trigger.contains((2, 1)) && trigger.contains((3, 2)));
+        assert!(!trigger.contains((4, 2)) && !trigger.contains((2, 0)));
+        assert_eq!(trigger.cells().count(), 4);
+    }
+}
@@ -0,0 +1,609 @@
+use raylib::prelude::*;
+use std::f32::consts::PI;
+
+use crate::combat::{in_hit_arc, Sword, DAMAGE, KNOCKBACK};
+use crate::enemy::Enemy;
+use crate::hazard::{hurts_on, occupied_cells, shift_walls, Hazard};
+use crate::input::{Action, InputState};
+use crate::level::Level;
+use crate::inventory::{Inventory, Item, KeyColor};
+use crate::maze::{cell_at, cell_center, get_cell, is_walkable, Maze};
+use crate::minimap::Automap;
+use crate::patrol::PatrolRoute;
+use crate::pathfinding::Cell;
+use crate::pickup::{Pickup, PickupKind, BOOST_TICKS, PICKUP_RANGE};
+use crate::player::{process_events, Player, MAX_HEALTH};
+use crate::rng::Rng;
+use crate::script::{Command, Script};
+use crate::sprites::Sprite;
+use crate::trigger::{Trigger, TriggerAction};
+
+pub const LIVES: u32 = 3;
+/// Hearts can't raise lives past this.
+pub const MAX_LIVES: u32 = 5;
+/// Enemies closer than this hurt the player on contact.
+const CONTACT_RANGE: f32 = 30.0;
+const CHECKPOINT_RANGE: f32 = 50.0;
+const PRINCESS_RANGE: f32 = 30.0;
+/// How far ahead of the player a door opens for a matching key.
+const DOOR_REACH: f32 = 30.0;
+
+/// How a run has gone so far, for the level-complete screen.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct RunStats {
+    pub deaths: u32,
+    pub kills: u32,
+    /// Enemies that saw the player at least once.
+    pub spotted_by: u32,
+}
+
+/// Things that happened during an update that the game reacts to with sound
+/// or a change of screen.
+#[derive(Clone, PartialEq, Debug)]
+pub enum WorldEvent {
+    PlayerHit,
+    /// The player ran out of health and went back to the last checkpoint.
+    LifeLost,
+    /// The last life was lost.
+    GameOver,
+    EnemyHit,
+    EnemyAlerted,
+    PickedUp(PickupKind),
+    /// A potion was drunk.
+    ItemUsed(Item),
+    DoorOpened(KeyColor),
+    /// Spikes or poison hurt the player or a goblin, or a pressure plate
+    /// shifted the walls.
+    Trap(Hazard),
+    /// The player walked into the level's trigger with this index.
+    Triggered(usize),
+    /// Text from a trigger or script to show on screen.
+    Message(String),
+    /// A sound file a trigger or script asked for.
+    Sound(String),
+    Won,
+}
+
+/// Everything that changes while playing a level. Starting over means
+/// building a fresh one from the same `Level`.
+pub struct World {
+    pub maze: Maze,
+    pub block_size: usize,
+    pub player: Player,
+    pub enemies: Vec<Enemy>,
+    /// Id for the next goblin a trigger or script spawns.
+    pub next_enemy_id: usize,
+    pub princess: Sprite,
+    pub checkpoints: Vec<Vector2>,
+    pub pickups: Vec<Pickup>,
+    /// Doors opened so far, which are open floor in `maze` now.
+    pub opened_doors: Vec<Cell>,
+    pub automap: Automap,
+    /// Seconds of play since the world was built.
+    pub elapsed: f32,
+    /// Updates since the world was built. Spikes and poison keep time by it.
+    pub tick: u32,
+    /// Pressure plates someone stood on last tick; stepping onto one that
+    /// isn't in here shifts the walls.
+    pub pressed_plates: Vec<Cell>,
+    pub triggers: Vec<Trigger>,
+    /// Index of the trigger behind every firing so far, in order.
+    pub fired_triggers: Vec<usize>,
+    /// Music a trigger or script switched to, if any.
+    pub music: Option<String>,
+    pub script: Option<Script>,
+    pub seed: u64,
+    pub stats: RunStats,
+}
+
+impl World {
+    /// Builds a fresh world. Everything random in the run derives from `seed`,
+    /// so the same seed and inputs always play out the same way.
+    pub fn new(level: &Level, block_size: usize, seed: u64) -> Self {
+        let player_spawn = cell_center(level.player, block_size);
+        let player = Player {
+            pos: player_spawn,
+            a: level.player_angle,
+            fov: PI / 3.0,
+            lives: LIVES,
+            noise: 0.0,
+            sword: Sword::default(),
+            health: MAX_HEALTH,
+            invulnerable: 0,
+            flash: 0,
+            knockback: Vector2::zero(),
+            checkpoint: player_spawn,
+            previous: (player_spawn, level.player_angle),
+            inventory: Inventory::default(),
+            speed_boost: 0,
+            invisible: 0,
+        };
+
+        let mut rng = Rng::new(seed);
+        let enemies = level
+            .enemies
+            .iter()
+            .enumerate()
+            .map(|(id, spawn)| {
+                let pos = cell_center(spawn.cell, block_size);
+                let mut route = spawn.route.clone();
+                route.reseed(rng.next_u64());
+                Enemy::new(id, pos.x, pos.y, route)
+            })
+            .collect();
+
+        let princess_pos = cell_center(level.princess, block_size);
+        let mut automap = Automap::new(&level.maze);
+        automap.reveal_view(&level.maze, player.pos, player.a, block_size);
+
+        World {
+            maze: level.maze.clone(),
+            block_size,
+            player,
+            enemies,
+            next_enemy_id: level.enemies.len(),
+            princess: Sprite::new(princess_pos.x, princess_pos.y, 0, 0, 'p', 14, 35),
+            checkpoints: level.checkpoints.iter().map(|&cell| cell_center(cell, block_size)).collect(),
+            pickups: level.pickups.iter().map(|&(kind, cell)| Pickup::new(kind, cell, block_size)).collect(),
+            opened_doors: Vec::new(),
+            automap,
+            elapsed: 0.0,
+            tick: 0,
+            pressed_plates: Vec::new(),
+            triggers: level.triggers.clone(),
+            fired_triggers: Vec::new(),
+            music: None,
+            script: level.script.clone().map(Script::new),
+            seed,
+            stats: RunStats::default(),
+        }
+    }
+
+    /// Advances the simulation by one tick of `dt` seconds with the actions in
+    /// `input`, returning what happened in order. Speeds and timers are tuned
+    /// per tick, so `dt` should always be `TICK`.
+    pub fn update(&mut self, dt: f32, input: &InputState) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        if self.tick == 0 {
+            self.run_script("on_start", &mut events);
+        }
+        self.elapsed += dt;
+        self.tick += 1;
+
+        self.player.previous = (self.player.pos, self.player.a);
+        for enemy in &mut self.enemies {
+            enemy.previous = enemy.pos();
+        }
+
+        process_events(input, &mut self.player, &self.maze, self.block_size);
+        self.drink_potions(input, &mut events);
+
+        if self.player.sword.update() {
+            for enemy in &mut self.enemies {
+                if !enemy.is_dying() && in_hit_arc(&self.maze, &self.player, enemy.pos(), self.block_size) {
+                    enemy.take_hit(DAMAGE, self.player.pos, KNOCKBACK);
+                    events.push(WorldEvent::EnemyHit);
+                    if enemy.is_dying() {
+                        self.stats.kills += 1;
+                    }
+                }
+            }
+        }
+
+        for enemy in &mut self.enemies {
+            if enemy.update(&self.maze, self.block_size, &self.player) {
+                events.push(WorldEvent::EnemyAlerted);
+                if !enemy.spotted {
+                    enemy.spotted = true;
+                    self.stats.spotted_by += 1;
+                }
+            }
+            if !enemy.is_dying()
+                && self.player.pos.distance_to(enemy.pos()) < CONTACT_RANGE
+                && self.player.take_hit(enemy.pos())
+            {
+                events.push(WorldEvent::PlayerHit);
+            }
+        }
+        self.spring_traps(&mut events);
+        self.enemies.retain(|enemy| !enemy.is_dead());
+
+        self.collect_pickups(&mut events);
+        self.open_door(&mut events);
+        self.check_triggers(&mut events);
+        self.run_script("on_tick", &mut events);
+
+        let player = &mut self.player;
+        if let Some(&checkpoint) = self.checkpoints.iter().find(|&&checkpoint| player.pos.distance_to(checkpoint) < CHECKPOINT_RANGE) {
+            player.checkpoint = checkpoint;
+        }
+
+        if player.health == 0 {
+            player.lives = player.lives.saturating_sub(1);
+            self.stats.deaths += 1;
+            events.push(if player.lives == 0 { WorldEvent::GameOver } else { WorldEvent::LifeLost });
+            player.respawn();
+            for enemy in &mut self.enemies {
+                enemy.reset_to_spawn();
+            }
+        }
+
+        // A trigger may have ended the level already.
+        if self.player.pos.distance_to(self.princess.pos) < PRINCESS_RANGE && !events.contains(&WorldEvent::Won) {
+            events.push(WorldEvent::Won);
+        }
+        self.automap.reveal_view(&self.maze, self.player.pos, self.player.a, self.block_size);
+
+        events
+    }
+
+    fn collect_pickups(&mut self, events: &mut Vec<WorldEvent>) {
+        let player = &mut self.player;
+        self.pickups.retain(|pickup| {
+            if player.pos.distance_to(pickup.sprite.pos) >= PICKUP_RANGE {
+                return true;
+            }
+            match pickup.kind {
+                PickupKind::Heart => player.lives = (player.lives + 1).min(MAX_LIVES),
+                PickupKind::Item(item) => player.inventory.add(item),
+            }
+            events.push(WorldEvent::PickedUp(pickup.kind));
+            false
+        });
+    }
+
+    /// Where the player and every goblin are.
+    pub fn bodies(&self) -> Vec<Vector2> {
+        std::iter::once(self.player.pos).chain(self.enemies.iter().map(Enemy::pos)).collect()
+    }
+
+    /// Pressure plates the player or a goblin is standing on.
+    pub fn plates_underfoot(&self) -> Vec<Cell> {
+        self.bodies()
+            .into_iter()
+            .filter(|pos| get_cell(&self.maze, pos.x, pos.y, self.block_size).and_then(Hazard::from_cell) == Some(Hazard::Plate))
+            .map(|pos| cell_at(pos, self.block_size))
+            .collect()
+    }
+
+    fn spring_traps(&mut self, events: &mut Vec<WorldEvent>) {
+        let (maze, block_size, tick) = (&self.maze, self.block_size, self.tick);
+        let hazard_at = |pos: Vector2| get_cell(maze, pos.x, pos.y, block_size).and_then(Hazard::from_cell);
+
+        let player = &mut self.player;
+        if let Some(hazard) = hazard_at(player.pos)
+            && hurts_on(hazard, tick)
+            && player.take_hit(player.pos)
+        {
+            events.push(WorldEvent::Trap(hazard));
+        }
+        for enemy in self.enemies.iter_mut().filter(|enemy| !enemy.is_dying()) {
+            if let Some(hazard) = hazard_at(enemy.pos())
+                && hurts_on(hazard, tick)
+            {
+                enemy.take_hit(DAMAGE, enemy.pos(), 0.0);
+                events.push(WorldEvent::Trap(hazard));
+                if enemy.is_dying() {
+                    self.stats.kills += 1;
+                }
+            }
+        }
+
+        let pressed = self.plates_underfoot();
+        if pressed.iter().any(|plate| !self.pressed_plates.contains(plate)) {
+            let bodies = self.bodies();
+            shift_walls(&mut self.maze, &bodies, self.block_size);
+            events.push(WorldEvent::Trap(Hazard::Plate));
+        }
+        self.pressed_plates = pressed;
+    }
+
+    fn drink_potions(&mut self, input: &InputState, events: &mut Vec<WorldEvent>) {
+        let player = &mut self.player;
+        if input.is_pressed(Action::DrinkSpeed) && player.inventory.take(Item::SpeedPotion) {
+            player.speed_boost = BOOST_TICKS;
+            events.push(WorldEvent::ItemUsed(Item::SpeedPotion));
+        }
+        if input.is_pressed(Action::DrinkInvisibility) && player.inventory.take(Item::InvisibilityPotion) {
+            player.invisible = BOOST_TICKS;
+            events.push(WorldEvent::ItemUsed(Item::InvisibilityPotion));
+        }
+    }
+
+    /// Spends a matching key on a door right in front of the player.
+    fn open_door(&mut self, events: &mut Vec<WorldEvent>) {
+        let player = &mut self.player;
+        let ahead = player.pos + Vector2::new(player.a.cos(), player.a.sin()) * DOOR_REACH;
+        let Some(color) = get_cell(&self.maze, ahead.x, ahead.y, self.block_size).and_then(KeyColor::from_door) else {
+            return;
+        };
+        if !player.inventory.take(Item::Key(color)) {
+            return;
+        }
+
+        self.open_cell(cell_at(ahead, self.block_size));
+        events.push(WorldEvent::DoorOpened(color));
+    }
+
+    fn open_cell(&mut self, (col, row): Cell) {
+        self.maze[row][col] = ' ';
+        self.opened_doors.push((col, row));
+    }
+
+    /// Fires the triggers the player just walked into.
+    fn check_triggers(&mut self, events: &mut Vec<WorldEvent>) {
+        let now = cell_at(self.player.pos, self.block_size);
+        let before = cell_at(self.player.previous.0, self.block_size);
+        for index in 0..self.triggers.len() {
+            let trigger = &self.triggers[index];
+            if !trigger.contains(now) || trigger.contains(before) || (!trigger.repeat && self.fired_triggers.contains(&index)) {
+                continue;
+            }
+            self.fired_triggers.push(index);
+            self.pull_trigger(index, events);
+        }
+    }
+
+    /// Carries out what a trigger does.
+    fn pull_trigger(&mut self, index: usize, events: &mut Vec<WorldEvent>) {
+        events.push(WorldEvent::Triggered(index));
+        match self.triggers[index].action.clone() {
+            TriggerAction::OpenDoor((col, row)) => {
+                if let Some(color) = KeyColor::from_door(self.maze[row][col]) {
+                    self.open_cell((col, row));
+                    events.push(WorldEvent::DoorOpened(color));
+                }
+            }
+            TriggerAction::Spawn(cells) => {
+                for cell in cells {
+                    self.spawn_enemy(cell);
+                }
+            }
+            TriggerAction::Message(text) => events.push(WorldEvent::Message(text)),
+            TriggerAction::Sound(file) => events.push(WorldEvent::Sound(file)),
+            TriggerAction::Music(file) => self.music = Some(file),
+            TriggerAction::EndLevel => events.push(WorldEvent::Won),
+            TriggerAction::Call(function) => self.run_script(&function, events),
+        }
+    }
+
+    /// Adds a goblin standing guard in `cell`.
+    fn spawn_enemy(&mut self, cell: Cell) {
+        let pos = cell_center(cell, self.block_size);
+        self.enemies.push(Enemy::new(self.next_enemy_id, pos.x, pos.y, PatrolRoute::idle()));
+        self.next_enemy_id += 1;
+    }
+
+    /// Calls a function of the level's script and carries out what it asked
+    /// for. A script that fails is reported and stopped.
+    fn run_script(&mut self, function: &str, events: &mut Vec<WorldEvent>) {
+        let Some(mut script) = self.script.take() else {
+            return;
+        };
+        match script.call(function, self) {
+            Ok(commands) => {
+                self.script = Some(script);
+                for command in commands {
+                    self.apply(command, events);
+                }
+            }
+            Err(err) => eprintln!("Level script stopped: {}", err),
+        }
+    }
+
+    fn apply(&mut self, command: Command, events: &mut Vec<WorldEvent>) {
+        let player = &mut self.player;
+        match command {
+            Command::Message(text) => events.push(WorldEvent::Message(text)),
+            Command::Sound(file) => events.push(WorldEvent::Sound(file)),
+            Command::Music(file) => self.music = Some(file),
+            Command::SetCell((col, row), cell) => {
+                // Walls don't appear on top of anyone.
+                if is_walkable(cell) || !occupied_cells(&self.bodies(), self.block_size).contains(&(col, row)) {
+                    self.maze[row][col] = cell;
+                }
+            }
+            Command::SpawnGoblin(cell) => self.spawn_enemy(cell),
+            Command::SetGoblinHealth(id, health) => {
+                if let Some(enemy) = self.enemies.iter_mut().find(|enemy| enemy.id == id && !enemy.is_dying()) {
+                    if health == 0 {
+                        enemy.take_hit(enemy.health, enemy.pos(), 0.0);
+                    } else {
+                        enemy.health = health;
+                    }
+                }
+            }
+            Command::SetGoblinSize(id, scale) => {
+                if let Some(enemy) = self.enemies.iter_mut().find(|enemy| enemy.id == id) {
+                    enemy.sprite.scale = scale;
+                }
+            }
+            Command::HurtPlayer => {
+                if player.take_hit(player.pos) {
+                    events.push(WorldEvent::PlayerHit);
+                }
+            }
+            Command::HealPlayer => player.health = (player.health + 1).min(MAX_HEALTH),
+            Command::Give(item) => player.inventory.add(item),
+            Command::Win => events.push(WorldEvent::Won),
+            Command::GameOver => {
+                player.lives = 1;
+                player.health = 0;
+            }
+        }
+    }
+
+    /// Runs `draw` with the player and enemies moved `alpha` of the way from
+    /// where they were before the last tick to where they are now, so motion
+    /// looks smooth when frames fall between ticks.
+    pub fn interpolated<R>(&mut self, alpha: f32, draw: impl FnOnce(&mut World) -> R) -> R {
+        let (pos, a) = (self.player.pos, self.player.a);
+        let (previous_pos, previous_a) = self.player.previous;
+        self.player.pos = previous_pos.lerp(pos, alpha);
+        self.player.a = previous_a + (a - previous_a) * alpha;
+
+        let enemies: Vec<Vector2> = self.enemies.iter().map(Enemy::pos).collect();
+        for enemy in &mut self.enemies {
+            enemy.sprite.pos = enemy.previous.lerp(enemy.sprite.pos, alpha);
+        }
+
+        let result = draw(self);
+
+        self.player.pos = pos;
+        self.player.a = a;
+        for (enemy, pos) in self.enemies.iter_mut().zip(enemies) {
+            enemy.sprite.pos = pos;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{DeviceState, InputBindings};
+    use crate::level::parse_level;
+    use crate::timestep::TICK;
+    use raylib::consts::KeyboardKey::{self, *};
+
+    const LEVEL: &str = "+----+\n|    |\n+----+\n\nplayer 1 1 0\nprincess 4 1\nenemy 3 1\n";
+
+    /// Input with `keys` held down and freshly pressed.
+    fn hold(keys: &[KeyboardKey]) -> InputState {
+        let devices = DeviceState { keys: keys.iter().copied().collect(), gamepad: None };
+        InputState::resolve(&InputBindings::defaults(), &devices, &InputState::default())
+    }
+
+    /// Steps the world until `done` returns true for an event, giving up
+    /// after `limit` steps. Returns every event seen on the way.
+    fn play(world: &mut World, input: &InputState, limit: usize, done: WorldEvent) -> Vec<WorldEvent> {
+        let mut seen = Vec::new();
+        for _ in 0..limit {
+            seen.extend(world.update(TICK, input));
+            if seen.contains(&done) {
+                return seen;
+            }
+        }
+        panic!("no {:?} after {} steps, saw {:?}", done, limit, seen);
+    }
+
+    #[test]
+    fn walking_to_the_princess_wins() {
+        let level = parse_level("test", "+------+\n|      |\n+------+\n\nplayer 1 1 0\nprincess 5 1\n");
+        let mut world = World::new(&level, 100, 0);
+
+        let events = play(&mut world, &hold(&[KEY_W]), 400, WorldEvent::Won);
+
+        assert_eq!(events, vec![WorldEvent::Won]);
+        assert!(world.elapsed > 1.0);
+    }
+
+    #[test]
+    fn a_key_opens_the_door_to_the_princess() {
+        let locked = "+------+\n|  R   |\n+------+\n\nplayer 1 1 0\nprincess 5 1\n";
+        // Only a red key fits.
+        let mut world = World::new(&parse_level("test", &format!("{}key 2 1\n", locked)), 100, 0);
+        for _ in 0..600 {
+            assert!(!world.update(TICK, &hold(&[KEY_W])).contains(&WorldEvent::Won));
+        }
+
+        let level = parse_level("test", &format!("{}key red 2 1\n", locked));
+        let mut world = World::new(&level, 100, 0);
+        let events = play(&mut world, &hold(&[KEY_W]), 600, WorldEvent::Won);
+
+        let red_key = Item::Key(KeyColor::Red);
+        assert_eq!(events, vec![WorldEvent::PickedUp(PickupKind::Item(red_key)), WorldEvent::DoorOpened(KeyColor::Red), WorldEvent::Won]);
+        assert_eq!(world.player.inventory.count(red_key), 0);
+        assert_eq!(world.opened_doors, vec![(3, 1)]);
+    }
+
+    #[test]
+    fn a_trigger_opens_the_door_to_the_princess() {
+        let level = parse_level("test", "+------+\n|   G  |\n+------+\n\nplayer 1 1 0\nprincess 5 1\ntrigger 2,1 open 4,1\n");
+        let mut world = World::new(&level, 100, 0);
+
+        let events = play(&mut world, &hold(&[KEY_W]), 600, WorldEvent::Won);
+
+        assert_eq!(events, vec![WorldEvent::Triggered(0), WorldEvent::DoorOpened(KeyColor::Green), WorldEvent::Won]);
+        assert_eq!(world.fired_triggers, vec![0]);
+    }
+
+    #[test]
+    fn a_pressure_plate_lowers_the_wall_to_the_princess() {
+        let level = parse_level("test", "+-------+\n| = #   |\n+-------+\n\nplayer 1 1 0\nprincess 6 1\n");
+        let mut world = World::new(&level, 100, 0);
+
+        let events = play(&mut world, &hold(&[KEY_W]), 600, WorldEvent::Won);
+
+        assert_eq!(events, vec![WorldEvent::Trap(Hazard::Plate), WorldEvent::Won]);
+        assert_eq!(world.maze[1][4], '_');
+    }
+
+    #[test]
+    fn standing_still_next_to_a_goblin_loses_every_life() {
+        let level = parse_level("test", "+------+\n|      |\n+------+\n\nplayer 1 1 0\nprincess 6 1\nenemy 5 1\n");
+        let mut world = World::new(&level, 100, 0);
+
+        let events = play(&mut world, &hold(&[]), 20_000, WorldEvent::GameOver);
+
+        assert_eq!(events[0], WorldEvent::EnemyAlerted);
+        assert_eq!(events.iter().filter(|event| **event == WorldEvent::PlayerHit).count(), (LIVES * MAX_HEALTH) as usize);
+        assert_eq!(events.iter().filter(|event| **event == WorldEvent::LifeLost).count(), LIVES as usize - 1);
+        assert_eq!(world.player.lives, 0);
+    }
+
+    #[test]
+    fn swinging_at_a_goblin_kills_it() {
+        let level = parse_level("test", "+------+\n|      |\n+------+\n\nplayer 1 1 0\nprincess 6 1\nenemy 2 1\n");
+        let mut world = World::new(&level, 100, 0);
+        let attack = hold(&[KEY_SPACE]);
+
+        let mut hits = 0;
+        for _ in 0..1200 {
+            hits += world.update(TICK, &attack).iter().filter(|event| **event == WorldEvent::EnemyHit).count();
+            if world.enemies.is_empty() {
+                break;
+            }
+        }
+
+        assert!(world.enemies.is_empty());
+        assert_eq!(hits, 3);
+    }
+
+    #[test]
+    fn rebuilding_restores_the_level() {
+        let level = parse_level("test", LEVEL);
+        let mut world = World::new(&level, 100, 0);
+        world.player.lives = 1;
+        world.player.pos = Vector2::new(350.0, 150.0);
+        world.enemies.clear();
+        // Behind the player, where the first look around doesn't reach.
+        world.automap.reveal(0, 1);
+
+        world = World::new(&level, 100, 0);
+
+        assert_eq!(world.player.lives, LIVES);
+        assert_eq!(world.player.health, MAX_HEALTH);
+        assert_eq!(world.player.pos, Vector2::new(150.0, 150.0));
+        assert_eq!(world.enemies.len(), 1);
+        assert_eq!(world.enemies[0].pos(), Vector2::new(350.0, 150.0));
+        assert!(!world.automap.is_seen(0, 1));
+        assert!(world.automap.is_seen(3, 1));
+    }
+
+    #[test]
+    fn scripts_keep_track_of_goblins_by_id() {
+        let level = parse_level("test", "+------+\n|      |\n+------+\n\nplayer 1 1 0\nprincess 6 1\nenemy 4 1\n");
+        let mut world = World::new(&level, 100, 0);
+        let source = "fn on_start() { this.boss = spawn_goblin(5, 1); } fn grow() { set_goblin_size(this.boss, 2.0); }";
+        world.script = Some(Script::new(Script::compile(source).unwrap()));
+        world.update(TICK, &hold(&[]));
+        // The level's own goblin dies, moving the boss up the list.
+        world.enemies.remove(0);
+
+        world.run_script("grow", &mut Vec::new());
+
+        assert_eq!(world.enemies[0].id, 1);
+        assert_eq!(world.enemies[0].sprite.scale, 2.0);
+    }
+}